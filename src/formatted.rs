@@ -0,0 +1,549 @@
+use std::fmt;
+
+use crate::parser::{html, mdv1, mdv2};
+use crate::parser::entities::{self, EntityError};
+use crate::parser::types::{MarkdownError, Node};
+use crate::render;
+
+/// The max length of a message text in UTF-16 code units.
+pub const MAX_LENGTH: usize = 4096;
+
+/// The max length of a media caption in UTF-16 code units.
+pub const MAX_CAPTION_LENGTH: usize = 1024;
+
+/// The Telegram parse modes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParseMode {
+    /// The legacy `Markdown` mode.
+    MarkdownV1,
+    /// The `MarkdownV2` mode.
+    MarkdownV2,
+    /// The `HTML` mode.
+    HTML
+}
+impl ParseMode {
+    /// Get the Bot API `parse_mode` value.
+    /// 
+    /// ### Example:
+    /// ```
+    /// use telemark::formatted::ParseMode;
+    /// 
+    /// assert_eq!(ParseMode::MarkdownV1.name(), "Markdown");
+    /// ```
+    pub fn name(&self) -> &'static str {
+        return match self {
+            ParseMode::MarkdownV1 => "Markdown",
+            ParseMode::MarkdownV2 => "MarkdownV2",
+            ParseMode::HTML => "HTML"
+        };
+    }
+}
+impl fmt::Display for ParseMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "{}", self.name());
+    }
+}
+
+/// The formatted text, It is the text that the recipient sees and its entities, like
+/// Telegram stores a message. The parsers build a `Node` and the renderers take a `Node`,
+/// the formatted text is a view of that AST that does not depend on any parse mode, so the
+/// conversion between the parse modes, the length and the split work on the entities. It is
+/// built from the AST of any parser and `to_node` gives back the AST. The entities are kept
+/// sorted by their offsets, the outer entity comes first and the overlapping entities are
+/// split. The entities are always inside the text and on its char boundaries.
+/// 
+/// ### Example:
+/// ```
+/// use telemark::formatted::{FormattedText, ParseMode};
+/// use telemark::parser::mdv2;
+/// 
+/// let formatted = FormattedText::parse("*bold* _😀_", ParseMode::MarkdownV1).unwrap();
+/// assert_eq!(formatted.text(), "bold 😀");
+/// assert_eq!(formatted.len(), 7);
+/// assert_eq!(formatted.render(ParseMode::HTML).unwrap(), "<b>bold</b> <i>😀</i>");
+/// assert_eq!(formatted.render(ParseMode::MarkdownV2).unwrap(), "*bold* _😀_");
+/// 
+/// let ast = mdv2::parser("*bold* _😀_").unwrap();
+/// assert_eq!(FormattedText::from(&ast), formatted);
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FormattedText {
+    text: String,
+    entities: Vec<MessageEntity>
+}
+impl FormattedText {
+    /// Create new `FormattedText`, the entities are checked against the text and sorted.
+    /// 
+    /// ### Example:
+    /// ```
+    /// use telemark::formatted::{EntityType, FormattedText, MessageEntity};
+    /// 
+    /// let formatted = FormattedText::new("ab", vec![
+    ///     MessageEntity::new(EntityType::Italic, 1, 1),
+    ///     MessageEntity::new(EntityType::Bold, 0, 2),
+    /// ]).unwrap();
+    /// assert_eq!(formatted.entities()[0].entity_type(), &EntityType::Bold);
+    /// 
+    /// assert!(FormattedText::new("ab", vec![MessageEntity::new(EntityType::Bold, 0, 3)]).is_err());
+    /// ```
+    pub fn new(text: &str, entities: Vec<MessageEntity>) -> Result<Self, EntityError> {
+        return Ok(Self::from_node(&entities::parser(text, &entities)?));
+    }
+
+    /// Create new `FormattedText` from an AST of any parser, the error nodes are a plain text.
+    pub fn from_node(node: &Node) -> Self {
+        let (text, entities) = render::entities::entities(node);
+        return Self {
+            text,
+            entities
+        };
+    }
+
+    /// Parse the document of the parse mode, It stops on the first error.
+    pub fn parse(document: &str, mode: ParseMode) -> Result<Self, MarkdownError> {
+        let node = match mode {
+            ParseMode::MarkdownV1 => mdv1::parser(document)?,
+            ParseMode::MarkdownV2 => mdv2::parser(document)?,
+            ParseMode::HTML => html::parser(document)?
+        };
+        return Ok(Self::from_node(&node));
+    }
+
+    /// Build the AST of the formatted text, the nodes positions are positions in the text.
+    /// It can not fail, the entities are built from an AST or checked by `new`, and `slice`
+    /// moves their ends out of the surrogate pairs, so they are always valid for the text.
+    pub fn to_node(&self) -> Node {
+        return entities::parser(&self.text, &self.entities)
+            .expect("the entities of a formatted text are valid for its text");
+    }
+
+    /// Render the formatted text to the source of the parse mode, the source parses back
+    /// to the same formatted text. It returns an error if the parse mode can not write it,
+    /// like the nested entities in the Markdown V1.
+    /// 
+    /// ### Example:
+    /// ```
+    /// use telemark::formatted::{FormattedText, ParseMode};
+    /// use telemark::parser::enums::MarkdownErrorType;
+    /// 
+    /// let formatted = FormattedText::parse("<b>a <i>b</i></b> (c", ParseMode::HTML).unwrap();
+    /// assert_eq!(formatted.render(ParseMode::MarkdownV2).unwrap(), "*a _b_* \\(c");
+    /// let err = formatted.render(ParseMode::MarkdownV1).unwrap_err();
+    /// assert_eq!(err.err(), &MarkdownErrorType::NestedEntity);
+    /// ```
+    pub fn render(&self, mode: ParseMode) -> Result<String, MarkdownError> {
+        let node = self.to_node();
+        return match mode {
+            ParseMode::MarkdownV1 => render::mdv1::serialize(&node),
+            ParseMode::MarkdownV2 => render::mdv2::serialize(&node),
+            ParseMode::HTML => Ok(render::html::render(&node))
+        };
+    }
+
+    /// Get the text that the recipient sees.
+    pub fn text(&self) -> &str {
+        return &self.text;
+    }
+
+    /// Get the entities.
+    pub fn entities(&self) -> &Vec<MessageEntity> {
+        return &self.entities;
+    }
+
+    /// Get the text length in UTF-16 code units, Telegram checks the `MAX_LENGTH` with it.
+    pub fn len(&self) -> usize {
+        return self.text.encode_utf16().count();
+    }
+
+    /// Check if the text is empty.
+    pub fn is_empty(&self) -> bool {
+        return self.text.is_empty();
+    }
+
+    /// Get the part of the formatted text in the range `start..end` of UTF-16 code units,
+    /// the entities are cut to the range. A range end inside a surrogate pair is moved
+    /// after it.
+    /// 
+    /// ### Example:
+    /// ```
+    /// use telemark::formatted::{FormattedText, ParseMode};
+    /// 
+    /// let formatted = FormattedText::parse("a *bold* b", ParseMode::MarkdownV1).unwrap();
+    /// assert_eq!(formatted.slice(4, 8).render(ParseMode::MarkdownV1).unwrap(), "*ld* b");
+    /// ```
+    pub fn slice(&self, start: usize, end: usize) -> Self {
+        let (start, end) = (self.unit(start), self.unit(end.max(start)));
+        let text = self.text[self.byte(start)..self.byte(end)].to_string();
+        let mut entities: Vec<MessageEntity> = Vec::new();
+        for e in &self.entities {
+            let (from, to) = (e.offset.max(start), (e.offset + e.length).min(end));
+            if from < to {
+                let mut entity = e.clone();
+                entity.offset = from - start;
+                entity.length = to - from;
+                entities.push(entity);
+            }
+        }
+        return Self {
+            text,
+            entities
+        };
+    }
+
+    /// Split the formatted text to parts of at most `max` UTF-16 code units, like a long
+    /// message that is sent as many messages. A part ends after the last new line, or else
+    /// after the last whitespace, that fits in it, the entities that cross the end are split
+    /// between the parts. Joining the parts texts gives the whole text.
+    /// 
+    /// ### Example:
+    /// ```
+    /// use telemark::formatted::{FormattedText, ParseMode};
+    /// 
+    /// let formatted = FormattedText::parse("*first line\nsecond* line", ParseMode::MarkdownV1).unwrap();
+    /// let parts: Vec<String> = formatted.split(15).iter().map(|p| p.render(ParseMode::HTML).unwrap()).collect();
+    /// assert_eq!(parts, vec!["<b>first line\n</b>", "<b>second</b> line"]);
+    /// ```
+    pub fn split(&self, max: usize) -> Vec<Self> {
+        // The UTF-16 end of every char.
+        let mut chars: Vec<(usize, char)> = Vec::new();
+        let mut length: usize = 0;
+        for c in self.text.chars() {
+            length += c.len_utf16();
+            chars.push((length, c));
+        }
+        let mut parts: Vec<Self> = Vec::new();
+        let mut start: usize = 0;
+        while length - start > max {
+            let from = chars.partition_point(|(end, _)| *end <= start);
+            let to = chars.partition_point(|(end, _)| *end <= start + max);
+            let window = &chars[from..to];
+            let end = window.iter().rev().find(|(_, c)| *c == '\n')
+                .or_else(|| window.iter().rev().find(|(_, c)| c.is_whitespace()))
+                .or_else(|| window.last())
+                // The char is longer than `max`, It is a part by itself.
+                .unwrap_or(&chars[from]).0;
+            parts.push(self.slice(start, end));
+            start = end;
+        }
+        parts.push(self.slice(start, length));
+        return parts;
+    }
+
+    /// Move the UTF-16 code unit out of a surrogate pair and into the text.
+    fn unit(&self, unit: usize) -> usize {
+        let mut end: usize = 0;
+        for c in self.text.chars() {
+            if end >= unit {
+                return end;
+            }
+            end += c.len_utf16();
+        }
+        return end;
+    }
+
+    /// Get the byte index of the UTF-16 code unit.
+    fn byte(&self, unit: usize) -> usize {
+        let mut end: usize = 0;
+        for (byte, c) in self.text.char_indices() {
+            if end >= unit {
+                return byte;
+            }
+            end += c.len_utf16();
+        }
+        return self.text.len();
+    }
+}
+impl From<&Node> for FormattedText {
+    fn from(node: &Node) -> Self {
+        return Self::from_node(node);
+    }
+}
+
+/// The Telegram message entity types.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EntityType {
+    Bold,
+    Italic,
+    Underline,
+    Strikethrough,
+    Spoiler,
+    Code,
+    Pre,
+    TextLink,
+    TextMention,
+    CustomEmoji,
+    Blockquote,
+    ExpandableBlockquote
+}
+impl EntityType {
+    /// Get the type of the Bot API name, It returns `None` for the types that Telegram
+    /// detects in the text by itself, like `mention` and `url`.
+    /// 
+    /// ### Example:
+    /// ```
+    /// use telemark::formatted::EntityType;
+    /// 
+    /// assert_eq!(EntityType::from_name("text_link"), Some(EntityType::TextLink));
+    /// assert_eq!(EntityType::from_name("hashtag"), None);
+    /// ```
+    pub fn from_name(name: &str) -> Option<Self> {
+        return match name {
+            "bold" => Some(EntityType::Bold),
+            "italic" => Some(EntityType::Italic),
+            "underline" => Some(EntityType::Underline),
+            "strikethrough" => Some(EntityType::Strikethrough),
+            "spoiler" => Some(EntityType::Spoiler),
+            "code" => Some(EntityType::Code),
+            "pre" => Some(EntityType::Pre),
+            "text_link" => Some(EntityType::TextLink),
+            "text_mention" => Some(EntityType::TextMention),
+            "custom_emoji" => Some(EntityType::CustomEmoji),
+            "blockquote" => Some(EntityType::Blockquote),
+            "expandable_blockquote" => Some(EntityType::ExpandableBlockquote),
+            _ => None
+        };
+    }
+
+    /// Get the Bot API name of the type.
+    /// 
+    /// ### Example:
+    /// ```
+    /// use telemark::formatted::EntityType;
+    /// 
+    /// assert_eq!(EntityType::TextLink.name(), "text_link");
+    /// ```
+    pub fn name(&self) -> &'static str {
+        return match self {
+            EntityType::Bold => "bold",
+            EntityType::Italic => "italic",
+            EntityType::Underline => "underline",
+            EntityType::Strikethrough => "strikethrough",
+            EntityType::Spoiler => "spoiler",
+            EntityType::Code => "code",
+            EntityType::Pre => "pre",
+            EntityType::TextLink => "text_link",
+            EntityType::TextMention => "text_mention",
+            EntityType::CustomEmoji => "custom_emoji",
+            EntityType::Blockquote => "blockquote",
+            EntityType::ExpandableBlockquote => "expandable_blockquote"
+        };
+    }
+}
+
+/// The Telegram message entity, the offset and the length are counted in UTF-16 code units.
+/// 
+/// ### Example:
+/// ```
+/// use telemark::formatted::{EntityType, MessageEntity};
+/// 
+/// let entity = MessageEntity::new(EntityType::TextLink, 0, 4).with_url("https://example.com");
+/// assert_eq!(entity.url(), Some("https://example.com"));
+/// assert_eq!(
+///     entity.to_json(),
+///     "{\"type\":\"text_link\",\"offset\":0,\"length\":4,\"url\":\"https://example.com\"}"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessageEntity {
+    entity_type: EntityType,
+    offset: usize,
+    length: usize,
+    url: Option<String>,
+    language: Option<String>,
+    user_id: Option<u64>,
+    custom_emoji_id: Option<u64>
+}
+impl MessageEntity {
+    /// Create new `MessageEntity`.
+    pub fn new(entity_type: EntityType, offset: usize, length: usize) -> Self {
+        return Self {
+            entity_type,
+            offset,
+            length,
+            url: None,
+            language: None,
+            user_id: None,
+            custom_emoji_id: None
+        };
+    }
+
+    /// Set the URL of the text link.
+    pub fn with_url(mut self, url: &str) -> Self {
+        self.url = Some(url.to_string());
+        return self;
+    }
+
+    /// Set the language of the pre.
+    pub fn with_language(mut self, language: &str) -> Self {
+        self.language = Some(language.to_string());
+        return self;
+    }
+
+    /// Set the user id of the text mention.
+    pub fn with_user_id(mut self, user_id: u64) -> Self {
+        self.user_id = Some(user_id);
+        return self;
+    }
+
+    /// Set the custom emoji id.
+    pub fn with_custom_emoji_id(mut self, custom_emoji_id: u64) -> Self {
+        self.custom_emoji_id = Some(custom_emoji_id);
+        return self;
+    }
+
+    /// Get the entity type.
+    pub fn entity_type(&self) -> &EntityType {
+        return &self.entity_type;
+    }
+
+    /// Get the offset in UTF-16 code units.
+    pub fn offset(&self) -> usize {
+        return self.offset;
+    }
+
+    /// Get the length in UTF-16 code units.
+    pub fn length(&self) -> usize {
+        return self.length;
+    }
+
+    /// Get the URL of the text link.
+    pub fn url(&self) -> Option<&str> {
+        return self.url.as_deref();
+    }
+
+    /// Get the language of the pre.
+    pub fn language(&self) -> Option<&str> {
+        return self.language.as_deref();
+    }
+
+    /// Get the user id of the text mention.
+    pub fn user_id(&self) -> Option<u64> {
+        return self.user_id;
+    }
+
+    /// Get the custom emoji id.
+    pub fn custom_emoji_id(&self) -> Option<u64> {
+        return self.custom_emoji_id;
+    }
+
+    /// Write the entity as a Bot API `MessageEntity` JSON object.
+    pub fn to_json(&self) -> String {
+        let mut json = format!(
+            "{{\"type\":\"{}\",\"offset\":{},\"length\":{}",
+            self.entity_type.name(), self.offset, self.length
+        );
+        if let Some(url) = &self.url {
+            json += &format!(",\"url\":{}", json_string(url));
+        }
+        if let Some(user_id) = self.user_id {
+            json += &format!(",\"user\":{{\"id\":{}}}", user_id);
+        }
+        if let Some(language) = &self.language {
+            json += &format!(",\"language\":{}", json_string(language));
+        }
+        if let Some(custom_emoji_id) = self.custom_emoji_id {
+            json += &format!(",\"custom_emoji_id\":\"{}\"", custom_emoji_id);
+        }
+        return json + "}";
+    }
+}
+
+/// Write the text as a JSON string.
+pub(crate) fn json_string(text: &str) -> String {
+    let mut json = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => json += "\\\"",
+            '\\' => json += "\\\\",
+            '\n' => json += "\\n",
+            '\r' => json += "\\r",
+            '\t' => json += "\\t",
+            c if (c as u32) < 0x20 => json += &format!("\\u{:04x}", c as u32),
+            _ => json.push(c)
+        }
+    }
+    return json + "\"";
+}
+
+/// The formatted text tests.
+#[cfg(test)]
+mod formatted_tests {
+    use super::{EntityType, FormattedText, MessageEntity, ParseMode};
+    use crate::parser::enums::MarkdownErrorType;
+
+    #[test]
+    fn convert() {
+        let formatted = FormattedText::parse(
+            "<b>bold <i>both</i></b> <a href=\"tg://user?id=7\">name</a> <pre><code class=\"language-rust\">let x;</code></pre>",
+            ParseMode::HTML
+        ).unwrap();
+        assert_eq!(formatted.text(), "bold both name let x;");
+        assert_eq!(
+            formatted.render(ParseMode::MarkdownV2).unwrap(),
+            "*bold _both_* [name](tg://user?id=7) ```rust\nlet x;```"
+        );
+        assert_eq!(
+            FormattedText::parse(&formatted.render(ParseMode::MarkdownV2).unwrap(), ParseMode::MarkdownV2).unwrap(),
+            formatted
+        );
+        assert_eq!(formatted.render(ParseMode::MarkdownV1).unwrap_err().err(), &MarkdownErrorType::NestedEntity);
+        // The V1 parser rejects an unmatched `(`.
+        let formatted = FormattedText::parse("a (c", ParseMode::HTML).unwrap();
+        assert_eq!(formatted.render(ParseMode::MarkdownV1).unwrap_err().err(), &MarkdownErrorType::ParenthesesOpen);
+        let formatted = FormattedText::parse("<b>a_b</b> (c) <a href=\"tg://user?id=7\">name</a>", ParseMode::HTML).unwrap();
+        let source = formatted.render(ParseMode::MarkdownV1).unwrap();
+        assert_eq!(source, "*a_b* (c) [name](tg://user?id=7)");
+        assert_eq!(FormattedText::parse(&source, ParseMode::MarkdownV1).unwrap(), formatted);
+    }
+
+    #[test]
+    fn normalized() {
+        // The italic overlaps the end of the bold.
+        let formatted = FormattedText::new("abcd", vec![
+            MessageEntity::new(EntityType::Italic, 1, 3),
+            MessageEntity::new(EntityType::Bold, 0, 2),
+        ]).unwrap();
+        assert_eq!(formatted.entities(), &vec![
+            MessageEntity::new(EntityType::Bold, 0, 2),
+            MessageEntity::new(EntityType::Italic, 1, 1),
+            MessageEntity::new(EntityType::Italic, 2, 2),
+        ]);
+        assert_eq!(FormattedText::from_node(&formatted.to_node()), formatted);
+    }
+
+    #[test]
+    fn split() {
+        let formatted = FormattedText::parse("*😀😀😀* abc", ParseMode::MarkdownV1).unwrap();
+        let parts = formatted.split(3);
+        let texts: Vec<&str> = parts.iter().map(|p| p.text()).collect();
+        assert_eq!(texts, vec!["😀", "😀", "😀 ", "abc"]);
+        assert!(parts.iter().all(|p| p.len() <= 3));
+        assert_eq!(parts[2].entities(), &vec![MessageEntity::new(EntityType::Bold, 0, 2)]);
+        assert!(parts[3].entities().is_empty());
+        // The char that is longer than the max is a part by itself.
+        let texts: Vec<String> = formatted.split(1).iter().map(|p| p.text().to_string()).collect();
+        assert_eq!(texts.concat(), formatted.text());
+        assert_eq!(texts[0], "😀");
+        assert_eq!(formatted.split(100), vec![formatted.clone()]);
+    }
+
+    #[test]
+    fn valid_entities() {
+        // `to_node` expects the entities of every formatted text to be valid for its text.
+        let formatted = FormattedText::parse(
+            "😀 <b>a<i>😀b</i></b><u>c</u> <tg-emoji emoji-id=\"5\">👍</tg-emoji><pre>😀\n</pre>",
+            ParseMode::HTML
+        ).unwrap();
+        for start in 0..=formatted.len() + 1 {
+            for end in 0..=formatted.len() + 1 {
+                let part = formatted.slice(start, end);
+                assert_eq!(FormattedText::from_node(&part.to_node()), part, "in {}..{}", start, end);
+                for max in 1..4 {
+                    for p in part.split(max) {
+                        assert_eq!(FormattedText::from_node(&p.to_node()), p);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,124 +1,202 @@
-use super::types::MDToken;
+use super::types::{MDToken, Position};
 use super::tokens::MDTokensType;
 use crate::reader::CharReader;
 
-/// The Markdown V1 Lexer.
+/// The Markdown V1 Lexer, It is a lazy iterator over the document tokens,
+/// It reads the document directly without copying it.
 /// 
 /// ### Example:
 /// ```
-/// use telemark::lexer::mdv1;
-/// use telemark::lexer::types::MDToken;
+/// use telemark::lexer::mdv1::Lexer;
 /// use telemark::lexer::tokens::MDTokensType;
 /// 
-/// let tokens = mdv1::lex("this is *bold*");
-/// assert_eq!(tokens.len(), 4);
-/// assert_eq!(tokens[0], MDToken::new(MDTokensType::Text, "this is ", 0));
-/// assert_eq!(tokens[1], MDToken::new(MDTokensType::Star, "*", 8));
-/// assert_eq!(tokens[2], MDToken::new(MDTokensType::Text, "bold", 9));
-/// assert_eq!(tokens[3], MDToken::new(MDTokensType::Star, "*", 13));
+/// let mut lexer = Lexer::new("*bold*");
+/// assert_eq!(lexer.next().unwrap().token_type(), &MDTokensType::Star);
+/// assert_eq!(lexer.next().unwrap().value(), "bold");
 /// ```
-pub fn lex(document: &str) -> Vec<MDToken> {
-    let mut char_reader = CharReader::new(document);
-    let mut tokens: Vec<MDToken> = Vec::new();
-    while let Some(c) = char_reader.next_char() {
-        match c {
-            '\\' => tokens.push(
-                MDToken::new(
-                    MDTokensType::Escape, 
-                    "\\", 
-                    char_reader.pos()
-                )
-            ),
-            '*'  => tokens.push(
-                MDToken::new(
-                    MDTokensType::Star, 
-                    "*", 
-                    char_reader.pos()
-                )
-            ),
-            '_'  => tokens.push(
-                MDToken::new(
-                    MDTokensType::Underscore, 
-                    "_", 
-                    char_reader.pos()
-                )
-            ),
-            '['  => tokens.push(
-                MDToken::new(
-                    MDTokensType::SquareBracketsOpen, 
-                    "[", 
-                    char_reader.pos()
-                )
-            ),
-            ']'  => tokens.push(
-                MDToken::new(
-                    MDTokensType::SquareBracketsClose, 
-                    "]", 
-                    char_reader.pos()
-                )
-            ),
-            '('  => tokens.push(
-                MDToken::new(
-                    MDTokensType::ParenthesesOpen, 
-                    "(", 
-                    char_reader.pos()
-                )
-            ),
-            ')'  => tokens.push(
-                MDToken::new(
-                    MDTokensType::ParenthesesClose, 
-                    ")", 
-                    char_reader.pos()
-                )
-            ),
+pub struct Lexer<'a> {
+    document: &'a str,
+    char_reader: CharReader<'a>,
+}
+impl<'a> Lexer<'a> {
+    /// Create new `Lexer`.
+    pub fn new(document: &'a str) -> Self {
+        return Self {
+            document,
+            char_reader: CharReader::new(document)
+        };
+    }
+
+    /// Create new `Lexer` that starts from `position` in the document,
+    /// the tokens positions are still relative to the start of the document.
+    /// 
+    /// ### Example:
+    /// ```
+    /// use telemark::lexer::mdv1::Lexer;
+    /// use telemark::lexer::types::Position;
+    /// 
+    /// let document = "*a* _b_";
+    /// let mut lexer = Lexer::from_position(document, Position::new(4, 4, 4));
+    /// assert_eq!(lexer.next().unwrap().position(), Position::new(4, 4, 4));
+    /// assert_eq!(lexer.next().unwrap().value(), "b");
+    /// ```
+    pub fn from_position(document: &'a str, position: Position) -> Self {
+        return Self {
+            document,
+            char_reader: CharReader::from_position(document, position)
+        };
+    }
+
+    /// Get the position of the next token, after the last token it is the end of the document.
+    /// 
+    /// ### Example:
+    /// ```
+    /// use telemark::lexer::mdv1::Lexer;
+    /// use telemark::lexer::types::Position;
+    /// 
+    /// let mut lexer = Lexer::new("*é");
+    /// lexer.next();
+    /// assert_eq!(lexer.next_position(), Position::new(1, 1, 1));
+    /// lexer.next();
+    /// assert_eq!(lexer.next_position(), Position::new(3, 2, 2));
+    /// ```
+    pub fn next_position(&self) -> Position {
+        return self.char_reader.next_position();
+    }
+
+    /// Move the char_reader to the end of the text block.
+    /// The end of the text is any Markdown V1 Token.
+    fn move_to_text_end(&mut self) {
+        let stops = ['\\', '*', '_', '`', '[', ']', '(', ')'];
+        while let Some(c) = self.char_reader.peek_char() {
+            if stops.contains(&c) {
+                break;
+            }
+            self.char_reader.next_char();
+        }
+    }
+}
+impl<'a> Iterator for Lexer<'a> {
+    type Item = MDToken<'a>;
+
+    fn next(&mut self) -> Option<MDToken<'a>> {
+        let start = self.char_reader.next_position();
+        let token = match self.char_reader.next_char()? {
+            '\\' => MDToken::with_position(MDTokensType::Escape, "\\", start),
+            '*'  => MDToken::with_position(MDTokensType::Star, "*", start),
+            '_'  => MDToken::with_position(MDTokensType::Underscore, "_", start),
+            '['  => MDToken::with_position(MDTokensType::SquareBracketsOpen, "[", start),
+            ']'  => MDToken::with_position(MDTokensType::SquareBracketsClose, "]", start),
+            '('  => MDToken::with_position(MDTokensType::ParenthesesOpen, "(", start),
+            ')'  => MDToken::with_position(MDTokensType::ParenthesesClose, ")", start),
             '`'  => {
-                if let Some(ncs) = char_reader.get_string(2) {
-                    if ncs.iter().collect::<String>() == "``" {
-                        char_reader.next_char(); // Delete the next `
-                        char_reader.next_char(); // Delete the next `
-                        tokens.push(
-                            MDToken::new(
-                                MDTokensType::Backticks, 
-                                "```", 
-                                char_reader.pos()
-                            )
-                        );
-                        continue;
-                    }
+                if self.char_reader.get_string(2) == Some("``") {
+                    self.char_reader.next_char(); // Delete the next `
+                    self.char_reader.next_char(); // Delete the next `
+                    MDToken::with_position(MDTokensType::Backticks, "```", start)
+                } else {
+                    MDToken::with_position(MDTokensType::Backtick, "`", start)
                 }
-                tokens.push(
-                    MDToken::new(
-                        MDTokensType::Backtick, 
-                        "`", 
-                        char_reader.pos()
-                    )
-                );
             }
             _ => {
-                let start_pos = char_reader.pos();
                 // Moving the char_reader to the end of the text.
-                move_to_text_end(&mut char_reader);
-                tokens.push(
-                    MDToken::new(
-                        MDTokensType::Text, 
-                        &document[start_pos..=char_reader.pos()],
-                        start_pos
-                    )
-                );
+                self.move_to_text_end();
+                MDToken::with_position(
+                    MDTokensType::Text, 
+                    &self.document[start.byte()..self.char_reader.next_position().byte()],
+                    start
+                )
             }
-        }
+        };
+        return Some(token);
     }
-    return tokens;
 }
 
-/// Move the char_reader to the end of the text block.
-/// The end of the text is any Markdown V1 Token.
-fn move_to_text_end(char_reader: &mut CharReader) {
-    let stops = ['\\', '*', '_', '`', '[', ']', '(', ')'];
-    while let Some(c) = char_reader.next_char() {
-        if let Some(_) = stops.iter().find(|&sc| sc == c) {
-            break;
+/// The Markdown V1 lexer, It returns a lazy `Lexer` iterator.
+/// 
+/// ### Example:
+/// ```
+/// use telemark::lexer::mdv1;
+/// use telemark::lexer::types::{MDToken, Position};
+/// use telemark::lexer::tokens::MDTokensType;
+/// 
+/// let tokens: Vec<MDToken> = mdv1::lex("this is *bold*").collect();
+/// assert_eq!(tokens.len(), 4);
+/// assert_eq!(tokens[0], MDToken::with_position(MDTokensType::Text, "this is ", Position::new(0, 0, 0)));
+/// assert_eq!(tokens[1], MDToken::with_position(MDTokensType::Star, "*", Position::new(8, 8, 8)));
+/// assert_eq!(tokens[2], MDToken::with_position(MDTokensType::Text, "bold", Position::new(9, 9, 9)));
+/// assert_eq!(tokens[3], MDToken::with_position(MDTokensType::Star, "*", Position::new(13, 13, 13)));
+/// ```
+pub fn lex(document: &str) -> Lexer<'_> {
+    return Lexer::new(document);
+}
+
+/// The Markdown V1 lexer tests.
+#[cfg(test)]
+mod mdv1_lexer_tests {
+    use super::lex;
+    use crate::lexer::tokens::MDTokensType;
+    use crate::lexer::types::Position;
+
+    /// Mixed-script documents, every one of them used to panic or produce wrong text.
+    const CORPUS: [&str; 12] = [
+        "مرحبا *بالعالم*",
+        "*عريض* و _مائل_",
+        "😀 *bold* 👋",
+        "👨‍👩‍👧 `code` 🇾🇪",
+        "café _naïve_ résumé",
+        "```rust\nlet é = \"😀\";\n```",
+        "[رابط](https://example.com/مسار)",
+        "\\*ليس عريض\\*",
+        "السؤال 1: ما هو ناتج 2*3؟",
+        "日本語 *太字* と _斜体_",
+        "e\u{301} *x* \u{200f}عربي\u{200e}",
+        "𝒳𝒴𝒵 [𝓁𝒾𝓃𝓀](tg://user?id=1)",
+    ];
+
+    #[test]
+    fn corpus_round_trip() {
+        for document in CORPUS {
+            let joined: String = lex(document).map(|t| t.value()).collect();
+            assert_eq!(joined, document);
         }
     }
-    char_reader.go_back();
+
+    #[test]
+    fn corpus_positions() {
+        for document in CORPUS {
+            let mut position = Position::default();
+            for token in lex(document) {
+                assert_eq!(token.position(), position, "in {:?}", document);
+                assert_eq!(
+                    &document[token.position().byte()..token.position().byte()+token.value().len()],
+                    token.value()
+                );
+                position = position.advance_str(token.value());
+            }
+            assert_eq!(position.byte(), document.len());
+            assert_eq!(position.char(), document.chars().count());
+            assert_eq!(position.utf16(), document.encode_utf16().count());
+        }
+    }
+
+    #[test]
+    fn arabic_bold() {
+        let tokens: Vec<_> = lex("مرحبا *بالعالم*").collect();
+        assert_eq!(tokens.len(), 4);
+        assert_eq!(tokens[0].value(), "مرحبا ");
+        assert_eq!(tokens[1].token_type(), &MDTokensType::Star);
+        assert_eq!(tokens[1].position(), Position::new(11, 6, 6));
+        assert_eq!(tokens[2].value(), "بالعالم");
+        assert_eq!(tokens[3].position(), Position::new(26, 14, 14));
+    }
+
+    #[test]
+    fn emoji_code() {
+        let tokens: Vec<_> = lex("😀 `x`").collect();
+        assert_eq!(tokens[0].value(), "😀 ");
+        assert_eq!(tokens[1].token_type(), &MDTokensType::Backtick);
+        assert_eq!(tokens[1].position(), Position::new(5, 2, 3));
+        assert_eq!(tokens[2].value(), "x");
+    }
 }
@@ -0,0 +1,209 @@
+use super::types::{MDToken, Position};
+use super::tokens::MDTokensType;
+use crate::reader::CharReader;
+
+/// The Markdown V2 reserved chars, they must be escaped outside the entities.
+pub const RESERVED: [char; 18] = [
+    '_', '*', '[', ']', '(', ')', '~', '`', '>', '#', '+', '-', '=', '|', '{', '}', '.', '!'
+];
+
+/// The Markdown V2 Lexer, It is a lazy iterator over the document tokens,
+/// It reads the document directly without copying it.
+/// 
+/// The double tokens (`**`, `__`, `||` and \`\`\`) are taken greedily from left to right,
+/// So `___` is lexed as `__` and then `_`, like Telegram does.
+/// 
+/// ### Example:
+/// ```
+/// use telemark::lexer::mdv2::Lexer;
+/// use telemark::lexer::tokens::MDTokensType;
+/// 
+/// let mut lexer = Lexer::new("||spoiler|| \\.");
+/// assert_eq!(lexer.next().unwrap().token_type(), &MDTokensType::DoublePipe);
+/// assert_eq!(lexer.next().unwrap().value(), "spoiler");
+/// assert_eq!(lexer.next().unwrap().token_type(), &MDTokensType::DoublePipe);
+/// assert_eq!(lexer.next().unwrap().value(), " ");
+/// assert_eq!(lexer.next().unwrap().value(), "\\.");
+/// ```
+pub struct Lexer<'a> {
+    document: &'a str,
+    char_reader: CharReader<'a>,
+}
+impl<'a> Lexer<'a> {
+    /// Create new `Lexer`.
+    pub fn new(document: &'a str) -> Self {
+        return Self {
+            document,
+            char_reader: CharReader::new(document)
+        };
+    }
+
+    /// Create new `Lexer` that starts from `position` in the document,
+    /// the tokens positions are still relative to the start of the document.
+    pub fn from_position(document: &'a str, position: Position) -> Self {
+        return Self {
+            document,
+            char_reader: CharReader::from_position(document, position)
+        };
+    }
+
+    /// Move the char_reader to the end of the text block.
+    /// The end of the text is any Markdown V2 Token.
+    fn move_to_text_end(&mut self) {
+        while let Some(c) = self.char_reader.peek_char() {
+            if c == '\\' || RESERVED.contains(&c) {
+                break;
+            }
+            self.char_reader.next_char();
+        }
+    }
+
+    /// Get the token of one char, or of two chars if the next char is the same.
+    fn single_or_double(
+        &mut self, 
+        c: char, 
+        single: MDTokensType, 
+        double: MDTokensType, 
+        start: Position
+    ) -> MDToken<'a> {
+        if self.char_reader.peek_char() == Some(c) {
+            self.char_reader.next_char();
+            return MDToken::with_position(double, self.slice(start), start);
+        }
+        return MDToken::with_position(single, self.slice(start), start);
+    }
+
+    /// Get the document slice from `start` to the current position.
+    fn slice(&self, start: Position) -> &'a str {
+        return &self.document[start.byte()..self.char_reader.next_position().byte()];
+    }
+}
+impl<'a> Iterator for Lexer<'a> {
+    type Item = MDToken<'a>;
+
+    fn next(&mut self) -> Option<MDToken<'a>> {
+        let start = self.char_reader.next_position();
+        let c = self.char_reader.next_char()?;
+        let token_type = match c {
+            '\\' => {
+                // Any char with code between 1 and 126 can be escaped.
+                if self.char_reader.peek_char().is_some_and(|nc| ('\u{1}'..='\u{7e}').contains(&nc)) {
+                    self.char_reader.next_char();
+                }
+                MDTokensType::Escape
+            },
+            '*' => return Some(self.single_or_double(c, MDTokensType::Star, MDTokensType::DoubleStar, start)),
+            '_' => return Some(self.single_or_double(c, MDTokensType::Underscore, MDTokensType::DoubleUnderscore, start)),
+            '|' => return Some(self.single_or_double(c, MDTokensType::Pipe, MDTokensType::DoublePipe, start)),
+            '`' => {
+                if self.char_reader.get_string(2) == Some("``") {
+                    self.char_reader.next_char(); // Delete the next `
+                    self.char_reader.next_char(); // Delete the next `
+                    MDTokensType::Backticks
+                } else {
+                    MDTokensType::Backtick
+                }
+            },
+            '[' => MDTokensType::SquareBracketsOpen,
+            ']' => MDTokensType::SquareBracketsClose,
+            '(' => MDTokensType::ParenthesesOpen,
+            ')' => MDTokensType::ParenthesesClose,
+            '~' => MDTokensType::Tilde,
+            '!' => MDTokensType::ExclamationMark,
+            '>' => MDTokensType::GreaterThan,
+            '#' => MDTokensType::Hash,
+            '+' => MDTokensType::Plus,
+            '-' => MDTokensType::Minus,
+            '=' => MDTokensType::Equal,
+            '{' => MDTokensType::CurlyBracketsOpen,
+            '}' => MDTokensType::CurlyBracketsClose,
+            '.' => MDTokensType::Dot,
+            _ => {
+                // Moving the char_reader to the end of the text.
+                self.move_to_text_end();
+                MDTokensType::Text
+            }
+        };
+        return Some(MDToken::with_position(token_type, self.slice(start), start));
+    }
+}
+
+/// The Markdown V2 lexer, It returns a lazy `Lexer` iterator.
+/// 
+/// ### Example:
+/// ```
+/// use telemark::lexer::mdv2;
+/// use telemark::lexer::types::{MDToken, Position};
+/// use telemark::lexer::tokens::MDTokensType;
+/// 
+/// let tokens: Vec<MDToken> = mdv2::lex("__underline__ 1\\.").collect();
+/// assert_eq!(tokens.len(), 5);
+/// assert_eq!(tokens[0], MDToken::with_position(MDTokensType::DoubleUnderscore, "__", Position::new(0, 0, 0)));
+/// assert_eq!(tokens[1], MDToken::with_position(MDTokensType::Text, "underline", Position::new(2, 2, 2)));
+/// assert_eq!(tokens[2], MDToken::with_position(MDTokensType::DoubleUnderscore, "__", Position::new(11, 11, 11)));
+/// assert_eq!(tokens[3], MDToken::with_position(MDTokensType::Text, " 1", Position::new(13, 13, 13)));
+/// assert_eq!(tokens[4], MDToken::with_position(MDTokensType::Escape, "\\.", Position::new(15, 15, 15)));
+/// ```
+pub fn lex(document: &str) -> Lexer<'_> {
+    return Lexer::new(document);
+}
+
+/// The Markdown V2 lexer tests.
+#[cfg(test)]
+mod mdv2_lexer_tests {
+    use super::{lex, RESERVED};
+    use crate::lexer::tokens::MDTokensType;
+    use crate::lexer::types::Position;
+
+    const CORPUS: [&str; 8] = [
+        "*bold \\*text*",
+        "_italic \\*text_ __underline__ ~strikethrough~ ||spoiler||",
+        "*bold _italic bold ~italic bold strikethrough ||spoiler||~ __underline italic bold___ bold*",
+        "[inline URL](http://www.example.com/) ![👍](tg://emoji?id=5368324170671202286)",
+        "`inline fixed-width code` ```python\nprint('hi')\n```",
+        ">Block quotation\n**>Expandable\n>quote||",
+        "مرحبا *بالعالم* 😀 \\😀 trailing \\",
+        "# + - = | { } . ! \\# \\+ \\- \\= \\| \\{ \\} \\. \\!",
+    ];
+
+    #[test]
+    fn corpus_round_trip() {
+        for document in CORPUS {
+            let mut position = Position::default();
+            for token in lex(document) {
+                assert_eq!(token.position(), position, "in {:?}", document);
+                position = position.advance_str(token.value());
+            }
+            assert_eq!(position.byte(), document.len());
+        }
+    }
+
+    #[test]
+    fn reserved() {
+        let types: Vec<MDTokensType> = lex(&RESERVED.iter().collect::<String>())
+            .map(|t| t.token_type().clone())
+            .collect();
+        assert_eq!(types, vec![
+            MDTokensType::Underscore, MDTokensType::Star, MDTokensType::SquareBracketsOpen,
+            MDTokensType::SquareBracketsClose, MDTokensType::ParenthesesOpen,
+            MDTokensType::ParenthesesClose, MDTokensType::Tilde, MDTokensType::Backtick,
+            MDTokensType::GreaterThan, MDTokensType::Hash, MDTokensType::Plus, MDTokensType::Minus,
+            MDTokensType::Equal, MDTokensType::Pipe, MDTokensType::CurlyBracketsOpen,
+            MDTokensType::CurlyBracketsClose, MDTokensType::Dot, MDTokensType::ExclamationMark,
+        ]);
+    }
+
+    #[test]
+    fn greedy_doubles() {
+        let values: Vec<&str> = lex("___a_\r__ ****||||").map(|t| t.value()).collect();
+        assert_eq!(values, vec!["__", "_", "a", "_", "\r", "__", " ", "**", "**", "||", "||"]);
+    }
+
+    #[test]
+    fn escapes() {
+        let tokens: Vec<_> = lex("\\\\\\_\\😀\\").collect();
+        let values: Vec<&str> = tokens.iter().map(|t| t.value()).collect();
+        assert_eq!(values, vec!["\\\\", "\\_", "\\", "😀", "\\"]);
+        assert!(tokens.iter().take(3).all(|t| *t.token_type() == MDTokensType::Escape));
+    }
+}
@@ -1,3 +1,4 @@
 pub mod mdv1;
+pub mod mdv2;
 pub mod tokens;
 pub mod types;
\ No newline at end of file
@@ -4,7 +4,7 @@
 pub enum MDTokensType {
     /// Any Text in the document
     Text,
-    /// \
+    /// \, In Markdown V2 the token holds the escaped char too, for example: `\*`.
     Escape,
     /// *
     Star,
@@ -33,5 +33,21 @@ pub enum MDTokensType {
     /// !
     ExclamationMark,
     /// \>
-    GreaterThan
+    GreaterThan,
+    /// \#
+    Hash,
+    /// \+
+    Plus,
+    /// \-
+    Minus,
+    /// =
+    Equal,
+    /// |
+    Pipe,
+    /// {
+    CurlyBracketsOpen,
+    /// }
+    CurlyBracketsClose,
+    /// .
+    Dot
 }
\ No newline at end of file
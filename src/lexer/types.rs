@@ -1,19 +1,132 @@
 use super::tokens::MDTokensType;
 
+/// The position in the document, It holds the same position counted in
+/// bytes, chars and UTF-16 code units. Telegram counts the entities offsets
+/// in UTF-16 code units and quotes a byte offset in its parsing errors.
+/// 
+/// ### Example:
+/// ```
+/// use telemark::lexer::types::Position;
+/// 
+/// let position = Position::default().advance('😀');
+/// assert_eq!(position.byte(), 4);
+/// assert_eq!(position.char(), 1);
+/// assert_eq!(position.utf16(), 2);
+/// ```
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub struct Position {
+    byte: usize,
+    char: usize,
+    utf16: usize
+}
+impl Position {
+    /// Create new `Position`.
+    pub fn new(byte: usize, char: usize, utf16: usize) -> Self {
+        return Self {
+            byte,
+            char,
+            utf16
+        };
+    }
+
+    /// Get the position after the char `c`.
+    /// 
+    /// ### Example:
+    /// ```
+    /// use telemark::lexer::types::Position;
+    /// 
+    /// let position = Position::new(1, 1, 1).advance('م');
+    /// assert_eq!(position, Position::new(3, 2, 2));
+    /// ```
+    pub fn advance(&self, c: char) -> Self {
+        return Self {
+            byte: self.byte + c.len_utf8(),
+            char: self.char + 1,
+            utf16: self.utf16 + c.len_utf16()
+        };
+    }
+
+    /// Get the position after the string `s`.
+    /// 
+    /// ### Example:
+    /// ```
+    /// use telemark::lexer::types::Position;
+    /// 
+    /// let position = Position::default().advance_str("hi 👋");
+    /// assert_eq!(position, Position::new(7, 4, 5));
+    /// ```
+    pub fn advance_str(&self, s: &str) -> Self {
+        return s.chars().fold(*self, |position, c| position.advance(c));
+    }
+
+    /// Get the offset in bytes.
+    /// 
+    /// ### Example:
+    /// ```
+    /// use telemark::lexer::types::Position;
+    /// 
+    /// assert_eq!(Position::new(4, 1, 2).byte(), 4);
+    /// ```
+    pub fn byte(&self) -> usize {
+        return self.byte;
+    }
+
+    /// Get the offset in chars (Unicode scalar values).
+    /// 
+    /// ### Example:
+    /// ```
+    /// use telemark::lexer::types::Position;
+    /// 
+    /// assert_eq!(Position::new(4, 1, 2).char(), 1);
+    /// ```
+    pub fn char(&self) -> usize {
+        return self.char;
+    }
+
+    /// Get the offset in UTF-16 code units.
+    /// 
+    /// ### Example:
+    /// ```
+    /// use telemark::lexer::types::Position;
+    /// 
+    /// assert_eq!(Position::new(4, 1, 2).utf16(), 2);
+    /// ```
+    pub fn utf16(&self) -> usize {
+        return self.utf16;
+    }
+}
+
 /// The Markdown Token Node.
 #[derive(PartialEq, Debug, Clone)]
 pub struct MDToken<'a> {
     token_type: MDTokensType,
     value: &'a str,
-    offset: usize
+    position: Position
 }
 impl<'a> MDToken<'a> {
-    /// Create new `MDToken`.
+    /// Create new `MDToken` at the offset in chars. The offset is used as the bytes and
+    /// the UTF-16 code units offsets too, so it is exact for an ASCII document, use
+    /// `with_position` to set the full position.
+    /// 
+    /// ### Example:
+    /// ```
+    /// use telemark::lexer::types::{MDToken, Position};
+    /// use telemark::lexer::tokens::MDTokensType;
+    /// 
+    /// let token = MDToken::new(MDTokensType::Star, "*", 5);
+    /// assert_eq!(token.offset(), 5);
+    /// assert_eq!(token.position(), Position::new(5, 5, 5));
+    /// ```
     pub fn new(token_type: MDTokensType, value: &'a str, offset: usize) -> Self {
+        return Self::with_position(token_type, value, Position::new(offset, offset, offset));
+    }
+
+    /// Create new `MDToken` at the position.
+    pub fn with_position(token_type: MDTokensType, value: &'a str, position: Position) -> Self {
         return Self {
             token_type,
             value,
-            offset
+            position
         };
     }
 
@@ -45,7 +158,7 @@ impl<'a> MDToken<'a> {
         return self.value;
     }
 
-    /// Get the token offset.
+    /// Get the token offset in chars, It is the `offset` of `new`.
     /// 
     /// ### Example:
     /// ```
@@ -56,6 +169,21 @@ impl<'a> MDToken<'a> {
     /// assert_eq!(token.offset(), 0);
     /// ```
     pub fn offset(&self) -> usize {
-        return self.offset;
+        return self.position.char();
+    }
+
+    /// Get the token position in bytes, chars and UTF-16 code units.
+    /// 
+    /// ### Example:
+    /// ```
+    /// use telemark::lexer::types::{MDToken, Position};
+    /// use telemark::lexer::tokens::MDTokensType;
+    /// 
+    /// let token = MDToken::with_position(MDTokensType::Star, "*", Position::new(4, 1, 2));
+    /// assert_eq!(token.position().byte(), 4);
+    /// assert_eq!(token.position().utf16(), 2);
+    /// ```
+    pub fn position(&self) -> Position {
+        return self.position;
     }
 }
\ No newline at end of file
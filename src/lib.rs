@@ -1,4 +1,5 @@
 
+#![allow(clippy::needless_return)]
 #![deny(
     unsafe_code,  
     unused_imports,
@@ -9,4 +10,6 @@
 
 pub mod lexer;
 pub mod parser;
+pub mod render;
+pub mod formatted;
 mod reader;
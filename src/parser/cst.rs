@@ -0,0 +1,484 @@
+use std::fmt;
+
+use crate::lexer::mdv1;
+use crate::lexer::types::{MDToken, Position};
+use crate::lexer::tokens::MDTokensType;
+use crate::reader::IterReader;
+
+/// The lossless syntax tree node kinds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    /// The start of the document.
+    Document,
+    /// A text that is written with Markdown tokens, for example: `[not a URL]` or `(text)`.
+    Text,
+    /// A Bold text, for example: `*bold text*`.
+    Bold,
+    /// A Italic text. for example: `_italic text_`.
+    Italic,
+    /// A Fixed Width Code, for example: \`fixed code\`.
+    FixedWidthCode,
+    /// A Pre-Fixed Width Code, for example: \`\`\`Pre-Fixed Width Code\`\`\`.
+    PreFormattedFixedWidthCode,
+    /// An Inline URL, for example: `[inline URL](https://example.com)`.
+    InlineURL,
+    /// A broken region, for example the `*` in `*not closed`.
+    Error
+}
+
+/// The lossless syntax tree token kinds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// Any visible text.
+    Text,
+    /// The escape char `\`.
+    Escape,
+    /// An entity delimiter, for example: `*`, `[` or \`\`\`.
+    Delimiter,
+    /// The Pre-Fixed Width Code language, for example: `rust`.
+    Language,
+    /// The `\n` after the Pre-Fixed Width Code language.
+    Newline
+}
+
+/// The lossless syntax tree token, It is a slice of the document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxToken<'a> {
+    kind: TokenKind,
+    text: &'a str,
+    position: Position
+}
+impl<'a> SyntaxToken<'a> {
+    /// Create new `SyntaxToken`.
+    pub fn new(kind: TokenKind, text: &'a str, position: Position) -> Self {
+        return Self {
+            kind,
+            text,
+            position
+        };
+    }
+
+    /// Get the token kind.
+    pub fn kind(&self) -> TokenKind {
+        return self.kind;
+    }
+
+    /// Get the token source text.
+    pub fn text(&self) -> &'a str {
+        return self.text;
+    }
+
+    /// Get the token position.
+    pub fn position(&self) -> Position {
+        return self.position;
+    }
+
+    /// Check if the token is trivia, the trivia tokens are not visible in the
+    /// message, for example: escapes and delimiters.
+    ///
+    /// ### Example:
+    /// ```
+    /// use telemark::parser::cst::{SyntaxToken, TokenKind};
+    /// use telemark::lexer::types::Position;
+    ///
+    /// assert!(SyntaxToken::new(TokenKind::Escape, "\\", Position::default()).is_trivia());
+    /// assert!(!SyntaxToken::new(TokenKind::Text, "text", Position::default()).is_trivia());
+    /// ```
+    pub fn is_trivia(&self) -> bool {
+        return matches!(self.kind, TokenKind::Escape | TokenKind::Delimiter | TokenKind::Newline);
+    }
+}
+
+/// The lossless syntax tree element, a node or a token.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyntaxElement<'a> {
+    /// An inner node.
+    Node(SyntaxNode<'a>),
+    /// A token.
+    Token(SyntaxToken<'a>)
+}
+impl<'a> SyntaxElement<'a> {
+    /// Get the element position.
+    pub fn position(&self) -> Position {
+        return match self {
+            SyntaxElement::Node(node) => node.position(),
+            SyntaxElement::Token(token) => token.position()
+        };
+    }
+}
+impl fmt::Display for SyntaxElement<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            SyntaxElement::Node(node) => write!(f, "{}", node),
+            SyntaxElement::Token(token) => write!(f, "{}", token.text())
+        };
+    }
+}
+
+/// The lossless syntax tree node, It keeps every token of the document
+/// (escapes and delimiters included), So the exact source can be reprinted
+/// with `to_string()`.
+///
+/// ### Example:
+/// ```
+/// use telemark::parser::cst::{self, NodeKind, SyntaxElement};
+///
+/// let tree = cst::parse("\\_ *bold*");
+/// assert_eq!(tree.kind(), NodeKind::Document);
+/// if let SyntaxElement::Node(bold) = &tree.children()[3] {
+///     assert_eq!(bold.kind(), NodeKind::Bold);
+///     assert_eq!(bold.to_string(), "*bold*");
+/// }
+/// assert_eq!(tree.to_string(), "\\_ *bold*");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxNode<'a> {
+    kind: NodeKind,
+    children: Vec<SyntaxElement<'a>>,
+    position: Position
+}
+impl<'a> SyntaxNode<'a> {
+    /// Create new `SyntaxNode`.
+    pub fn new(kind: NodeKind, position: Position) -> Self {
+        return Self {
+            kind,
+            children: Vec::new(),
+            position
+        };
+    }
+
+    /// Add element to the children list.
+    pub fn add_child(&mut self, child: SyntaxElement<'a>) {
+        self.children.push(child);
+    }
+
+    /// Get the node kind.
+    pub fn kind(&self) -> NodeKind {
+        return self.kind;
+    }
+
+    /// Get the children list.
+    pub fn children(&self) -> &Vec<SyntaxElement<'a>> {
+        return &self.children;
+    }
+
+    /// Get the node position.
+    pub fn position(&self) -> Position {
+        return self.position;
+    }
+
+    /// Get all the tokens of the node in the source order.
+    ///
+    /// ### Example:
+    /// ```
+    /// use telemark::parser::cst::{self, TokenKind};
+    ///
+    /// let tree = cst::parse("```rust\nlet x;```");
+    /// let kinds: Vec<TokenKind> = tree.tokens().iter().map(|t| t.kind()).collect();
+    /// assert_eq!(kinds, vec![
+    ///     TokenKind::Delimiter, TokenKind::Language, TokenKind::Newline,
+    ///     TokenKind::Text, TokenKind::Delimiter
+    /// ]);
+    /// ```
+    pub fn tokens(&self) -> Vec<&SyntaxToken<'a>> {
+        let mut tokens: Vec<&SyntaxToken<'a>> = Vec::new();
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => tokens.extend(node.tokens()),
+                SyntaxElement::Token(token) => tokens.push(token)
+            }
+        }
+        return tokens;
+    }
+}
+impl fmt::Display for SyntaxNode<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for child in &self.children {
+            write!(f, "{}", child)?;
+        }
+        return Ok(());
+    }
+}
+
+/// The Markdown V1 lossless parser, It never fails, the unclosed entities
+/// are kept as `NodeKind::Error` nodes and the parsing continue after them.
+///
+/// ### Example:
+/// ```
+/// use telemark::parser::cst::{self, NodeKind, SyntaxElement};
+///
+/// let document = "*not closed _italic_";
+/// let tree = cst::parse(document);
+/// if let SyntaxElement::Node(err) = &tree.children()[0] {
+///     assert_eq!(err.kind(), NodeKind::Error);
+///     assert_eq!(err.to_string(), "*");
+/// }
+/// assert_eq!(tree.to_string(), document);
+/// ```
+pub fn parse(document: &str) -> SyntaxNode<'_> {
+    let mut builder = Builder {
+        document,
+        tokens: IterReader::new(mdv1::lex(document))
+    };
+    let mut root = SyntaxNode::new(NodeKind::Document, Position::default());
+    while let Some(t) = builder.tokens.next().cloned() {
+        if *t.token_type() == MDTokensType::Escape {
+            // The backslash escapes only the formatting tokens, otherwise It is a text.
+            match builder.escaped(None) {
+                Some(escaped) => {
+                    root.add_child(token(TokenKind::Escape, &t));
+                    root.add_child(token(TokenKind::Text, &escaped));
+                },
+                None => root.add_child(token(TokenKind::Text, &t))
+            }
+            continue;
+        }
+        let child = match t.token_type() {
+            MDTokensType::Star => builder.entity(NodeKind::Bold, &t, MDTokensType::Star),
+            MDTokensType::Underscore => builder.entity(NodeKind::Italic, &t, MDTokensType::Underscore),
+            MDTokensType::Backtick => builder.entity(NodeKind::FixedWidthCode, &t, MDTokensType::Backtick),
+            MDTokensType::Backticks => builder.pre(&t),
+            MDTokensType::SquareBracketsOpen => builder.inline_url(&t),
+            MDTokensType::ParenthesesOpen => builder.parentheses(&t),
+            _ => token(TokenKind::Text, &t)
+        };
+        root.add_child(child);
+    }
+    return root;
+}
+
+/// The Markdown V1 tokens that the backslash escapes.
+const ESCAPED: [MDTokensType; 5] = [
+    MDTokensType::Star, MDTokensType::Underscore, MDTokensType::Backtick,
+    MDTokensType::Backticks, MDTokensType::SquareBracketsOpen
+];
+
+/// Create a token element from the lexer token.
+fn token<'a>(kind: TokenKind, t: &MDToken<'a>) -> SyntaxElement<'a> {
+    return SyntaxElement::Token(SyntaxToken::new(kind, t.value(), t.position()));
+}
+
+/// The lossless syntax tree builder, It reads the tokens lazily from the lexer.
+struct Builder<'a> {
+    document: &'a str,
+    tokens: IterReader<mdv1::Lexer<'a>>
+}
+impl<'a> Builder<'a> {
+    /// Get the token after the backslash if the backslash escapes it, It escapes the
+    /// formatting tokens and the `to` token of the entity. Otherwise the token is not read.
+    fn escaped(&mut self, to: Option<&MDTokensType>) -> Option<MDToken<'a>> {
+        let t = self.tokens.next()?.clone();
+        if ESCAPED.contains(t.token_type()) || Some(t.token_type()) == to {
+            return Some(t);
+        }
+        self.tokens.go_back();
+        return None;
+    }
+
+    /// Add the tokens until the not escaped `to` token to the node as inner tokens, It returns
+    /// the `to` token or `None` if the document ends before it.
+    fn until(&mut self, node: &mut SyntaxNode<'a>, to: &MDTokensType) -> Option<MDToken<'a>> {
+        while let Some(t) = self.tokens.next().cloned() {
+            if *t.token_type() == MDTokensType::Escape {
+                match self.escaped(Some(to)) {
+                    Some(escaped) => {
+                        node.add_child(token(TokenKind::Escape, &t));
+                        node.add_child(token(TokenKind::Text, &escaped));
+                    },
+                    None => node.add_child(token(TokenKind::Text, &t))
+                }
+                continue;
+            }
+            if t.token_type() == to {
+                return Some(t);
+            }
+            node.add_child(token(TokenKind::Text, &t));
+        }
+        return None;
+    }
+
+    /// Create an error node from the unclosed opening token, the lexer continues after it.
+    fn error(&mut self, open: &MDToken<'a>) -> SyntaxElement<'a> {
+        self.resync(open);
+        let mut node = SyntaxNode::new(NodeKind::Error, open.position());
+        node.add_child(token(TokenKind::Delimiter, open));
+        return SyntaxElement::Node(node);
+    }
+
+    /// Continue lexing after the token.
+    fn resync(&mut self, t: &MDToken<'a>) {
+        let position = t.position().advance_str(t.value());
+        self.tokens = IterReader::new(mdv1::Lexer::from_position(self.document, position));
+    }
+
+    /// Build a simple entity node like `*bold*`.
+    fn entity(&mut self, kind: NodeKind, open: &MDToken<'a>, to: MDTokensType) -> SyntaxElement<'a> {
+        let mut node = SyntaxNode::new(kind, open.position());
+        node.add_child(token(TokenKind::Delimiter, open));
+        let Some(close) = self.until(&mut node, &to) else {
+            return self.error(open);
+        };
+        node.add_child(token(TokenKind::Delimiter, &close));
+        return SyntaxElement::Node(node);
+    }
+
+    /// Build the Pre-Fixed Width Code node and split its language line.
+    fn pre(&mut self, open: &MDToken<'a>) -> SyntaxElement<'a> {
+        let SyntaxElement::Node(node) = self.entity(
+            NodeKind::PreFormattedFixedWidthCode, open, MDTokensType::Backticks
+        ) else {
+            return self.error(open);
+        };
+        let has_newline = node.children.iter().any(|c| matches!(
+            c, SyntaxElement::Token(t) if t.kind() == TokenKind::Text && t.text().contains('\n')
+        ));
+        if !has_newline {
+            return SyntaxElement::Node(node);
+        }
+        let mut pre = SyntaxNode::new(node.kind, node.position);
+        let mut in_language = true;
+        for child in node.children {
+            match child {
+                SyntaxElement::Token(t) if in_language && t.kind() == TokenKind::Text => {
+                    let Some(i) = t.text().find('\n') else {
+                        pre.add_child(SyntaxElement::Token(
+                            SyntaxToken::new(TokenKind::Language, t.text(), t.position())
+                        ));
+                        continue;
+                    };
+                    let (language, code) = (&t.text()[..i], &t.text()[i+1..]);
+                    let newline = t.position().advance_str(language);
+                    if !language.is_empty() {
+                        pre.add_child(SyntaxElement::Token(
+                            SyntaxToken::new(TokenKind::Language, language, t.position())
+                        ));
+                    }
+                    pre.add_child(SyntaxElement::Token(
+                        SyntaxToken::new(TokenKind::Newline, "\n", newline)
+                    ));
+                    if !code.is_empty() {
+                        pre.add_child(SyntaxElement::Token(
+                            SyntaxToken::new(TokenKind::Text, code, newline.advance('\n'))
+                        ));
+                    }
+                    in_language = false;
+                },
+                _ => pre.add_child(child)
+            }
+        }
+        return SyntaxElement::Node(pre);
+    }
+
+    /// Build the Inline URL node, or a text node if the title is not followed by `(`.
+    fn inline_url(&mut self, open: &MDToken<'a>) -> SyntaxElement<'a> {
+        let mut node = SyntaxNode::new(NodeKind::Text, open.position());
+        node.add_child(token(TokenKind::Delimiter, open));
+        let Some(title_end) = self.until(&mut node, &MDTokensType::SquareBracketsClose) else {
+            return self.error(open);
+        };
+        node.add_child(token(TokenKind::Delimiter, &title_end));
+        match self.tokens.next().cloned() {
+            Some(url_start) if *url_start.token_type() == MDTokensType::ParenthesesOpen => {
+                node.add_child(token(TokenKind::Delimiter, &url_start));
+                let title = node.children.len();
+                match self.until(&mut node, &MDTokensType::ParenthesesClose) {
+                    Some(url_end) => {
+                        node.add_child(token(TokenKind::Delimiter, &url_end));
+                        node.kind = NodeKind::InlineURL;
+                    },
+                    None => {
+                        // Keep the `[title](` in the error node and continue after it.
+                        node.children.truncate(title);
+                        node.kind = NodeKind::Error;
+                        self.resync(&url_start);
+                    }
+                }
+            },
+            Some(_) => self.tokens.go_back(),
+            None => {}
+        }
+        return SyntaxElement::Node(node);
+    }
+
+    /// Build the parentheses text node, for example: `(text)`.
+    fn parentheses(&mut self, open: &MDToken<'a>) -> SyntaxElement<'a> {
+        let mut node = SyntaxNode::new(NodeKind::Text, open.position());
+        node.add_child(token(TokenKind::Text, open));
+        let Some(close) = self.until(&mut node, &MDTokensType::ParenthesesClose) else {
+            return self.error(open);
+        };
+        node.add_child(token(TokenKind::Text, &close));
+        return SyntaxElement::Node(node);
+    }
+}
+
+/// The lossless syntax tree tests.
+#[cfg(test)]
+mod cst_tests {
+    use super::{parse, NodeKind, SyntaxElement, TokenKind};
+
+    const CORPUS: [&str; 14] = [
+        "",
+        "plain text",
+        "*bold* _italic_ `code`",
+        "\\*not bold\\* \\\\*bold*",
+        "```\ncode\n```",
+        "```rust\nfn main() {}\n```",
+        "```rust```",
+        "[title](https://example.com) [not a url] (text)",
+        "*not closed _italic_",
+        "[title](not closed",
+        "[not closed",
+        "``` not closed",
+        "مرحبا *بالعالم* 😀 [رابط](https://example.com)",
+        "\\a \\\\ \\[x](y)",
+    ];
+
+    #[test]
+    fn round_trip() {
+        for document in CORPUS {
+            assert_eq!(parse(document).to_string(), document);
+        }
+    }
+
+    #[test]
+    fn positions() {
+        for document in CORPUS {
+            for t in parse(document).tokens() {
+                let byte = t.position().byte();
+                assert_eq!(&document[byte..byte + t.text().len()], t.text());
+            }
+        }
+    }
+
+    #[test]
+    fn unclosed_url() {
+        let tree = parse("[a](b *c*");
+        let kinds: Vec<NodeKind> = tree.children().iter().filter_map(|c| match c {
+            SyntaxElement::Node(node) => Some(node.kind()),
+            SyntaxElement::Token(_) => None
+        }).collect();
+        assert_eq!(kinds, vec![NodeKind::Error, NodeKind::Bold]);
+    }
+
+    #[test]
+    fn escapes() {
+        // Only the formatting tokens and the closing token are escaped.
+        let tree = parse("\\a \\\\* `\\`\\d`");
+        let kinds: Vec<(TokenKind, &str)> = tree.tokens().iter().map(|t| (t.kind(), t.text())).collect();
+        assert_eq!(kinds, vec![
+            (TokenKind::Text, "\\"),
+            (TokenKind::Text, "a "),
+            (TokenKind::Text, "\\"),
+            (TokenKind::Escape, "\\"),
+            (TokenKind::Text, "*"),
+            (TokenKind::Text, " "),
+            (TokenKind::Delimiter, "`"),
+            (TokenKind::Escape, "\\"),
+            (TokenKind::Text, "`"),
+            (TokenKind::Text, "\\"),
+            (TokenKind::Text, "d"),
+            (TokenKind::Delimiter, "`"),
+        ]);
+    }
+}
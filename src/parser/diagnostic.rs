@@ -0,0 +1,310 @@
+use crate::lexer::types::Position;
+use super::types::MarkdownError;
+
+/// The ANSI styles of the colored diagnostics.
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// The tab is shown as 4 spaces.
+const TAB: &str = "    ";
+
+/// Get the line and the column of the position in the source, both of them start from 1
+/// and the column is the display width of the line before the position, So the carets
+/// are aligned in the terminal.
+/// 
+/// ### Example:
+/// ```
+/// use telemark::parser::diagnostic::line_column;
+/// use telemark::lexer::types::Position;
+/// 
+/// let source = "first line\nمرحبا *bold";
+/// assert_eq!(line_column(source, Position::new(22, 17, 17)), (2, 7));
+/// 
+/// let source = "你好 *bold";
+/// assert_eq!(line_column(source, Position::new(7, 3, 3)), (1, 6));
+/// ```
+pub fn line_column(source: &str, position: Position) -> (usize, usize) {
+    let before = &source[..position.byte().min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let column = display_width(&before[line_start..]) + 1;
+    return (line, column);
+}
+
+/// Get the width of the text in the terminal columns, The wide chars (CJK and emoji)
+/// take 2 columns, the combining marks and the zero width chars take none and the
+/// tab takes 4 columns.
+/// 
+/// ### Example:
+/// ```
+/// use telemark::parser::diagnostic::display_width;
+/// 
+/// assert_eq!(display_width("abc"), 3);
+/// assert_eq!(display_width("日本"), 4);
+/// assert_eq!(display_width("😀 a"), 4);
+/// assert_eq!(display_width("\ta"), 5);
+/// ```
+pub fn display_width(text: &str) -> usize {
+    return text.chars().map(|c| match c as u32 {
+        0x09 => TAB.len(),
+        0x0300..=0x036f
+        | 0x0483..=0x0489
+        | 0x0591..=0x05bd
+        | 0x0610..=0x061a
+        | 0x064b..=0x065f
+        | 0x0670
+        | 0x06d6..=0x06dc
+        | 0x06df..=0x06e4
+        | 0x1ab0..=0x1aff
+        | 0x1dc0..=0x1dff
+        | 0x200b..=0x200f
+        | 0x20d0..=0x20ff
+        | 0xfe00..=0xfe0f
+        | 0xfe20..=0xfe2f
+        | 0xe0100..=0xe01ef => 0,
+        0x1100..=0x115f
+        | 0x231a..=0x231b
+        | 0x2329..=0x232a
+        | 0x23e9..=0x23ec
+        | 0x2614..=0x2615
+        | 0x26a1
+        | 0x26aa..=0x26ab
+        | 0x26bd..=0x26be
+        | 0x2705
+        | 0x270a..=0x270b
+        | 0x2728
+        | 0x274c
+        | 0x2753..=0x2755
+        | 0x2795..=0x2797
+        | 0x2b50
+        | 0x2e80..=0x303e
+        | 0x3041..=0x33ff
+        | 0x3400..=0x4dbf
+        | 0x4e00..=0x9fff
+        | 0xa000..=0xa4cf
+        | 0xac00..=0xd7a3
+        | 0xf900..=0xfaff
+        | 0xfe30..=0xfe4f
+        | 0xff00..=0xff60
+        | 0xffe0..=0xffe6
+        | 0x1f004
+        | 0x1f0cf
+        | 0x1f18e
+        | 0x1f191..=0x1f19a
+        | 0x1f200..=0x1f251
+        | 0x1f300..=0x1f64f
+        | 0x1f680..=0x1f6ff
+        | 0x1f7e0..=0x1f7eb
+        | 0x1f90c..=0x1f9ff
+        | 0x1fa70..=0x1faff
+        | 0x20000..=0x3fffd => 2,
+        _ => 1
+    }).sum();
+}
+
+/// The diagnostic label, a message that points to a position in the source.
+struct Label {
+    position: Position,
+    message: String,
+    primary: bool
+}
+
+/// The diagnostic renderer, It converts the `MarkdownError` to a readable
+/// message with the line and the column, the source line of the error and
+/// a caret that points to the opening delimiter and to where the closing
+/// delimiter was expected.
+/// 
+/// ### Example:
+/// ```
+/// use telemark::parser::mdv1;
+/// use telemark::parser::diagnostic::Diagnostic;
+/// 
+/// let source = "this is *bold";
+/// let err = mdv1::parser(source).unwrap_err();
+/// assert_eq!(
+///     Diagnostic::new(source).render(&err),
+///     concat!(
+///         "error: unclosed bold, the star `*` is open\n",
+///         " --> 1:9\n",
+///         "  |\n",
+///         "1 | this is *bold\n",
+///         "  |         ^ the entity starts here\n",
+///         "  |              - expected `*` here\n",
+///     )
+/// );
+/// ```
+pub struct Diagnostic<'a> {
+    source: &'a str,
+    color: bool
+}
+impl<'a> Diagnostic<'a> {
+    /// Create new plain `Diagnostic` for the source.
+    pub fn new(source: &'a str) -> Self {
+        return Self {
+            source,
+            color: false
+        };
+    }
+
+    /// Enable or disable the ANSI colors.
+    /// 
+    /// ### Example:
+    /// ```
+    /// use telemark::parser::mdv1;
+    /// use telemark::parser::diagnostic::Diagnostic;
+    /// 
+    /// let err = mdv1::parser("_italic").unwrap_err();
+    /// let output = Diagnostic::new("_italic").colored(true).render(&err);
+    /// assert!(output.starts_with("\x1b[1;31merror\x1b[0m"));
+    /// ```
+    pub fn colored(mut self, color: bool) -> Self {
+        self.color = color;
+        return self;
+    }
+
+    /// Render the error.
+    pub fn render(&self, err: &MarkdownError) -> String {
+        let mut labels: Vec<Label> = vec![Label {
+            position: err.position(),
+            message: if err.err().closer().is_some() { "the entity starts here" } else { "here" }.to_string(),
+            primary: true
+        }];
+        if let Some(expected) = err.expected() {
+            labels.push(Label {
+                position: expected,
+                message: match err.err().closer() {
+                    Some(closer) => format!("expected `{}` here", closer),
+                    None => "expected here".to_string()
+                },
+                primary: false
+            });
+        }
+        let (line, column) = line_column(self.source, err.position());
+        let last_line = labels.iter()
+            .map(|l| line_column(self.source, l.position).0)
+            .max()
+            .unwrap_or(line);
+        let width = last_line.to_string().len();
+        let gutter = " ".repeat(width);
+
+        let mut output = format!(
+            "{}: {}\n{}--> {}:{}\n{} {}\n",
+            self.style("error", RED),
+            self.style(&err.err().to_string(), BOLD),
+            gutter, line, column,
+            gutter, self.style("|", BLUE)
+        );
+        let mut printed: Option<usize> = None;
+        for label in &labels {
+            let (line, column) = line_column(self.source, label.position);
+            if printed != Some(line) {
+                if printed.is_some() {
+                    output += &format!("{} {}\n", gutter, self.style("|", BLUE));
+                }
+                output += &format!(
+                    "{} {} {}\n",
+                    self.style(&format!("{:>width$}", line, width = width), BLUE),
+                    self.style("|", BLUE),
+                    self.line(line)
+                );
+                printed = Some(line);
+            }
+            let (mark, style) = if label.primary { ("^", RED) } else { ("-", BLUE) };
+            output += &format!(
+                "{} {} {}{}\n",
+                gutter,
+                self.style("|", BLUE),
+                " ".repeat(column - 1),
+                self.style(&format!("{} {}", mark, label.message), style)
+            );
+        }
+        return output;
+    }
+
+    /// Render all the errors, separated by empty lines.
+    /// 
+    /// ### Example:
+    /// ```
+    /// use telemark::parser::mdv1;
+    /// use telemark::parser::diagnostic::Diagnostic;
+    /// 
+    /// let source = "*a\n_b";
+    /// let (_, errors) = mdv1::parse_all(source);
+    /// let output = Diagnostic::new(source).render_all(&errors);
+    /// assert!(output.contains(" --> 1:1\n"));
+    /// assert!(output.contains(" --> 2:1\n"));
+    /// ```
+    pub fn render_all(&self, errors: &[MarkdownError]) -> String {
+        return errors.iter()
+            .map(|err| self.render(err))
+            .collect::<Vec<String>>()
+            .join("\n");
+    }
+
+    /// Get the source line, it starts from 1. The tabs are expanded to match the columns.
+    fn line(&self, line: usize) -> String {
+        let text = self.source.split('\n').nth(line - 1).unwrap_or("");
+        return text.strip_suffix('\r').unwrap_or(text).replace('\t', TAB);
+    }
+
+    /// Style the text if the colors are enabled.
+    fn style(&self, text: &str, style: &str) -> String {
+        if !self.color {
+            return text.to_string();
+        }
+        return format!("{}{}{}", style, text, RESET);
+    }
+}
+
+/// The diagnostic renderer tests.
+#[cfg(test)]
+mod diagnostic_tests {
+    use super::{display_width, line_column, Diagnostic};
+    use crate::lexer::types::Position;
+    use crate::parser::mdv1;
+
+    #[test]
+    fn line_column_end() {
+        assert_eq!(line_column("a\n", Position::new(2, 2, 2)), (2, 1));
+        assert_eq!(line_column("", Position::default()), (1, 1));
+    }
+
+    #[test]
+    fn wide_chars() {
+        assert_eq!(display_width("e\u{301}"), 1);
+        let source = "😀\t中 _a";
+        let err = mdv1::parser(source).unwrap_err();
+        assert_eq!(
+            Diagnostic::new(source).render(&err),
+            concat!(
+                "error: unclosed italic, the underscore `_` is open\n",
+                " --> 1:10\n",
+                "  |\n",
+                "1 | 😀    中 _a\n",
+                "  |          ^ the entity starts here\n",
+                "  |            - expected `_` here\n",
+            )
+        );
+    }
+
+    #[test]
+    fn multiline() {
+        let source = "*bold\n😀 line\nlast";
+        let err = mdv1::parser(source).unwrap_err();
+        assert_eq!(
+            Diagnostic::new(source).render(&err),
+            concat!(
+                "error: unclosed bold, the star `*` is open\n",
+                " --> 1:1\n",
+                "  |\n",
+                "1 | *bold\n",
+                "  | ^ the entity starts here\n",
+                "  |\n",
+                "3 | last\n",
+                "  |     - expected `*` here\n",
+            )
+        );
+    }
+}
@@ -0,0 +1,708 @@
+use std::fmt;
+
+use crate::lexer::types::Position;
+use crate::reader::CharReader;
+use crate::formatted::{EntityType, MessageEntity};
+
+use super::types::{entity, visible_text, Node};
+use super::enums::Markdown;
+
+/// The message entities import errors.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EntityErrorType {
+    /// The JSON is not valid, for example: `[{"type": "bold",}]`.
+    InvalidJSON,
+    /// The required field is missing, for example: the `url` of a `text_link`.
+    MissingField(String),
+    /// The field has a wrong value, for example: `"offset": -1`.
+    InvalidField(String),
+    /// The entity ends after the end of the text.
+    OutOfRange,
+    /// The entity starts or ends inside a surrogate pair, for example: in the middle of `😀`.
+    InvalidBoundary
+}
+impl fmt::Display for EntityErrorType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            EntityErrorType::InvalidJSON => write!(f, "the JSON is not valid"),
+            EntityErrorType::MissingField(name) => write!(f, "the field `{}` is missing", name),
+            EntityErrorType::InvalidField(name) => write!(f, "the field `{}` is not valid", name),
+            EntityErrorType::OutOfRange => write!(f, "the entity ends after the end of the text"),
+            EntityErrorType::InvalidBoundary => write!(f, "the entity starts or ends inside a surrogate pair")
+        };
+    }
+}
+
+/// The message entities import error, for the JSON errors the offset is counted in bytes
+/// from the start of the JSON, for the entity range errors it is the entity offset in
+/// UTF-16 code units.
+/// 
+/// ### Example:
+/// ```
+/// use telemark::parser::entities::{self, EntityErrorType};
+/// 
+/// let err = entities::from_json("[{\"type\": \"bold\", \"offset\": 0}]").unwrap_err();
+/// assert_eq!(err.err(), &EntityErrorType::MissingField("length".to_string()));
+/// assert_eq!(err.offset(), 1);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntityError {
+    err: EntityErrorType,
+    offset: usize
+}
+impl EntityError {
+    /// Create new `EntityError`.
+    pub fn new(err: EntityErrorType, offset: usize) -> Self {
+        return Self {
+            err,
+            offset
+        };
+    }
+
+    /// Get the error type.
+    pub fn err(&self) -> &EntityErrorType {
+        return &self.err;
+    }
+
+    /// Get the error offset.
+    pub fn offset(&self) -> usize {
+        return self.offset;
+    }
+}
+impl fmt::Display for EntityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "{} at {}", self.err, self.offset);
+    }
+}
+
+/// Build the AST of the text and its message entities, like Telegram sends them. The
+/// nested entities become inner nodes, and an entity that overlaps the end of its parent
+/// is split in two. The entities inside a code, a pre or a custom emoji are dropped. The
+/// nodes positions are positions in the text.
+/// 
+/// ### Example:
+/// ```
+/// use telemark::parser::entities;
+/// use telemark::formatted::{EntityType, MessageEntity};
+/// use telemark::render::{html, mdv2};
+/// 
+/// // *bold _both* italic_
+/// let ast = entities::parser("bold both italic", &[
+///     MessageEntity::new(EntityType::Bold, 0, 9),
+///     MessageEntity::new(EntityType::Italic, 5, 11),
+/// ]).unwrap();
+/// assert_eq!(html::render(&ast), "<b>bold <i>both</i></b><i> italic</i>");
+/// assert_eq!(mdv2::serialize(&ast).unwrap(), "*bold _both_*_ italic_");
+/// ```
+pub fn parser(text: &str, entities: &[MessageEntity]) -> Result<Node, EntityError> {
+    let mut units: Vec<Option<usize>> = Vec::with_capacity(text.len() + 1);
+    for (byte, c) in text.char_indices() {
+        units.push(Some(byte));
+        if c.len_utf16() == 2 {
+            units.push(None);
+        }
+    }
+    units.push(Some(text.len()));
+    let mut builder = Builder {
+        text,
+        entities,
+        units,
+        spans: vec![Span { entity: None, start: 0, end: text.encode_utf16().count(), children: Vec::new() }]
+    };
+    builder.spans()?;
+    return Ok(builder.node(0));
+}
+
+/// Read the message entities from a Bot API JSON array, the entity types that Telegram
+/// detects in the text by itself, like `mention` and `url`, are skipped.
+/// 
+/// ### Example:
+/// ```
+/// use telemark::parser::entities;
+/// use telemark::formatted::EntityType;
+/// 
+/// let list = entities::from_json(concat!(
+///     "[{\"type\": \"mention\", \"offset\": 0, \"length\": 4},",
+///     " {\"type\": \"pre\", \"offset\": 5, \"length\": 6, \"language\": \"rust\"}]"
+/// )).unwrap();
+/// assert_eq!(list.len(), 1);
+/// assert_eq!(list[0].entity_type(), &EntityType::Pre);
+/// assert_eq!(list[0].language(), Some("rust"));
+/// ```
+pub fn from_json(json: &str) -> Result<Vec<MessageEntity>, EntityError> {
+    return entities(&read_json(json)?);
+}
+
+/// Build the AST of a text field of a Bot API JSON object, like a `Message` or a `Poll`.
+/// The entities are read from the `entities` field for the `text`, and from the
+/// `<field>_entities` field for the others, like `caption` and `explanation`.
+/// 
+/// ### Example:
+/// ```
+/// use telemark::parser::entities;
+/// use telemark::render::mdv1;
+/// 
+/// let poll = concat!(
+///     "{\"question\": \"2+2?\", \"type\": \"quiz\", \"explanation\": \"It is 4\",",
+///     " \"explanation_entities\": [{\"type\": \"bold\", \"offset\": 6, \"length\": 1}]}"
+/// );
+/// let ast = entities::parse_json(poll, "explanation").unwrap();
+/// assert_eq!(mdv1::serialize(&ast).unwrap(), "It is *4*");
+/// ```
+pub fn parse_json(json: &str, field: &str) -> Result<Node, EntityError> {
+    let object = read_json(json)?;
+    let Json::Object(_) = object.json else {
+        return Err(EntityError::new(EntityErrorType::InvalidJSON, object.offset));
+    };
+    let text = required(&object, field)?;
+    let Json::String(text) = &text.json else {
+        return Err(EntityError::new(EntityErrorType::InvalidField(field.to_string()), text.offset));
+    };
+    let name = if field == "text" { "entities".to_string() } else { format!("{}_entities", field) };
+    let list = match object.get(&name) {
+        Some(value) => entities(value)?,
+        None => Vec::new()
+    };
+    return parser(text, &list);
+}
+
+/// The JSON value.
+enum Json {
+    Null,
+    /// The `true` or the `false`, the value is not used.
+    Bool,
+    /// The number source, It is converted when it is used.
+    Number(String),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>)
+}
+
+/// The JSON value and its offset in bytes.
+struct Value {
+    json: Json,
+    offset: usize
+}
+impl Value {
+    /// Get the field of the object.
+    fn get(&self, name: &str) -> Option<&Value> {
+        let Json::Object(fields) = &self.json else {
+            return None;
+        };
+        return fields.iter().find(|(n, _)| n == name).map(|(_, v)| v);
+    }
+
+    /// Get the value as a string.
+    fn string(&self, name: &str) -> Result<&str, EntityError> {
+        return match &self.json {
+            Json::String(value) => Ok(value),
+            _ => Err(EntityError::new(EntityErrorType::InvalidField(name.to_string()), self.offset))
+        };
+    }
+
+    /// Get the value as a not negative integer, the ids can be numbers or strings.
+    fn integer(&self, name: &str) -> Result<u64, EntityError> {
+        let number = match &self.json {
+            Json::Number(value) | Json::String(value) => value.parse::<u64>().ok(),
+            _ => None
+        };
+        return number.ok_or_else(|| EntityError::new(EntityErrorType::InvalidField(name.to_string()), self.offset));
+    }
+}
+
+/// Get the required field of the object.
+fn required<'v>(object: &'v Value, name: &str) -> Result<&'v Value, EntityError> {
+    return object.get(name)
+        .ok_or_else(|| EntityError::new(EntityErrorType::MissingField(name.to_string()), object.offset));
+}
+
+/// Read the message entities of the JSON array.
+fn entities(array: &Value) -> Result<Vec<MessageEntity>, EntityError> {
+    let Json::Array(items) = &array.json else {
+        return Err(EntityError::new(EntityErrorType::InvalidField("entities".to_string()), array.offset));
+    };
+    let mut list: Vec<MessageEntity> = Vec::new();
+    for item in items {
+        let Json::Object(_) = item.json else {
+            return Err(EntityError::new(EntityErrorType::InvalidField("entities".to_string()), item.offset));
+        };
+        let name = required(item, "type")?.string("type")?;
+        let offset = required(item, "offset")?.integer("offset")? as usize;
+        let length = required(item, "length")?.integer("length")? as usize;
+        let Some(entity_type) = EntityType::from_name(name) else {
+            continue;
+        };
+        let mut entity = MessageEntity::new(entity_type.clone(), offset, length);
+        match entity_type {
+            EntityType::TextLink => entity = entity.with_url(required(item, "url")?.string("url")?),
+            EntityType::TextMention => {
+                let user = required(item, "user")?;
+                entity = entity.with_user_id(required(user, "id")?.integer("id")?);
+            },
+            EntityType::CustomEmoji => {
+                entity = entity.with_custom_emoji_id(required(item, "custom_emoji_id")?.integer("custom_emoji_id")?);
+            },
+            EntityType::Pre => if let Some(language) = item.get("language") {
+                entity = entity.with_language(language.string("language")?);
+            },
+            _ => {}
+        }
+        list.push(entity);
+    }
+    return Ok(list);
+}
+
+/// Read the JSON document, It must be one value.
+fn read_json(json: &str) -> Result<Value, EntityError> {
+    let mut reader = JsonReader { chars: CharReader::new(json), depth: 0 };
+    let value = reader.value()?;
+    reader.spaces();
+    if reader.chars.peek_char().is_some() {
+        return Err(reader.invalid());
+    }
+    return Ok(value);
+}
+
+/// The max depth of the nested JSON objects and arrays.
+const MAX_DEPTH: usize = 64;
+
+/// The JSON reader, It reads the values that the Bot API sends.
+struct JsonReader<'a> {
+    chars: CharReader<'a>,
+    /// The number of the open objects and arrays.
+    depth: usize
+}
+impl<'a> JsonReader<'a> {
+    /// Read a value.
+    fn value(&mut self) -> Result<Value, EntityError> {
+        self.spaces();
+        let offset = self.chars.next_position().byte();
+        let json = match self.chars.peek_char() {
+            Some('{') => self.nested(Self::object)?,
+            Some('[') => self.nested(Self::array)?,
+            Some('"') => Json::String(self.string()?),
+            Some('t') => self.literal("true", Json::Bool)?,
+            Some('f') => self.literal("false", Json::Bool)?,
+            Some('n') => self.literal("null", Json::Null)?,
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number()?,
+            _ => return Err(self.invalid())
+        };
+        return Ok(Value { json, offset });
+    }
+
+    /// Read a nested object or array, the depth is limited so a deep document can not
+    /// overflow the stack.
+    fn nested(&mut self, read: fn(&mut Self) -> Result<Json, EntityError>) -> Result<Json, EntityError> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.invalid());
+        }
+        self.depth += 1;
+        let json = read(self)?;
+        self.depth -= 1;
+        return Ok(json);
+    }
+
+    /// Read an object.
+    fn object(&mut self) -> Result<Json, EntityError> {
+        self.expect('{')?;
+        let mut fields: Vec<(String, Value)> = Vec::new();
+        self.spaces();
+        if self.chars.peek_char() == Some('}') {
+            self.chars.next_char();
+            return Ok(Json::Object(fields));
+        }
+        loop {
+            self.spaces();
+            let name = self.string()?;
+            self.spaces();
+            self.expect(':')?;
+            fields.push((name, self.value()?));
+            self.spaces();
+            match self.chars.peek_char() {
+                Some(',') => self.chars.next_char(),
+                Some('}') => {
+                    self.chars.next_char();
+                    return Ok(Json::Object(fields));
+                },
+                _ => return Err(self.invalid())
+            };
+        }
+    }
+
+    /// Read an array.
+    fn array(&mut self) -> Result<Json, EntityError> {
+        self.expect('[')?;
+        let mut items: Vec<Value> = Vec::new();
+        self.spaces();
+        if self.chars.peek_char() == Some(']') {
+            self.chars.next_char();
+            return Ok(Json::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.spaces();
+            match self.chars.peek_char() {
+                Some(',') => self.chars.next_char(),
+                Some(']') => {
+                    self.chars.next_char();
+                    return Ok(Json::Array(items));
+                },
+                _ => return Err(self.invalid())
+            };
+        }
+    }
+
+    /// Read a string and decode its escapes.
+    fn string(&mut self) -> Result<String, EntityError> {
+        self.expect('"')?;
+        let mut value = String::new();
+        loop {
+            let offset = self.chars.next_position().byte();
+            let c = match self.chars.next_char() {
+                Some('"') => return Ok(value),
+                Some('\\') => match self.chars.next_char() {
+                    Some('"') => '"',
+                    Some('\\') => '\\',
+                    Some('/') => '/',
+                    Some('b') => '\u{8}',
+                    Some('f') => '\u{c}',
+                    Some('n') => '\n',
+                    Some('r') => '\r',
+                    Some('t') => '\t',
+                    Some('u') => {
+                        let unit = self.hex()?;
+                        // The chars out of the BMP are escaped as a surrogate pair.
+                        let code = if (0xD800..0xDC00).contains(&unit) && self.chars.get_string(2) == Some("\\u") {
+                            self.chars.next_char();
+                            self.chars.next_char();
+                            0x10000 + ((unit - 0xD800) << 10) + (self.hex()?.wrapping_sub(0xDC00) & 0x3FF)
+                        } else {
+                            unit
+                        };
+                        char::from_u32(code).ok_or(EntityError::new(EntityErrorType::InvalidJSON, offset))?
+                    },
+                    _ => return Err(EntityError::new(EntityErrorType::InvalidJSON, offset))
+                },
+                Some(c) if (c as u32) >= 0x20 => c,
+                _ => return Err(EntityError::new(EntityErrorType::InvalidJSON, offset))
+            };
+            value.push(c);
+        }
+    }
+
+    /// Read the four hex digits of the `\u` escape.
+    fn hex(&mut self) -> Result<u32, EntityError> {
+        let error = self.invalid();
+        let digits = self.chars.get_string(4).filter(|d| d.len() == 4).ok_or(error.clone())?;
+        let unit = u32::from_str_radix(digits, 16).map_err(|_| error)?;
+        for _ in 0..4 {
+            self.chars.next_char();
+        }
+        return Ok(unit);
+    }
+
+    /// Read a number, It is kept as it is written.
+    fn number(&mut self) -> Result<Json, EntityError> {
+        let error = self.invalid();
+        let mut number = String::new();
+        while let Some(c) = self.chars.peek_char()
+            && (c.is_ascii_digit() || ['-', '+', '.', 'e', 'E'].contains(&c)) {
+            number.push(c);
+            self.chars.next_char();
+        }
+        if number.parse::<f64>().is_err() {
+            return Err(error);
+        }
+        return Ok(Json::Number(number));
+    }
+
+    /// Read the `true`, the `false` or the `null`.
+    fn literal(&mut self, literal: &str, json: Json) -> Result<Json, EntityError> {
+        for expected in literal.chars() {
+            self.expect(expected)?;
+        }
+        return Ok(json);
+    }
+
+    /// Read the `expected` char.
+    fn expect(&mut self, expected: char) -> Result<(), EntityError> {
+        if self.chars.peek_char() != Some(expected) {
+            return Err(self.invalid());
+        }
+        self.chars.next_char();
+        return Ok(());
+    }
+
+    /// Skip the whitespaces.
+    fn spaces(&mut self) {
+        while let Some(c) = self.chars.peek_char()
+            && [' ', '\t', '\n', '\r'].contains(&c) {
+            self.chars.next_char();
+        }
+    }
+
+    /// Get the invalid JSON error at the current position.
+    fn invalid(&self) -> EntityError {
+        return EntityError::new(EntityErrorType::InvalidJSON, self.chars.next_position().byte());
+    }
+}
+
+/// The text range of an entity, the range is counted in UTF-16 code units.
+struct Span {
+    /// The entity index, the root has no entity.
+    entity: Option<usize>,
+    start: usize,
+    end: usize,
+    /// The inner spans indices, they are sorted by their starts.
+    children: Vec<usize>
+}
+
+/// The AST builder of the text and its entities.
+struct Builder<'a> {
+    text: &'a str,
+    entities: &'a [MessageEntity],
+    /// The byte index of every UTF-16 code unit, It is `None` inside a surrogate pair.
+    units: Vec<Option<usize>>,
+    spans: Vec<Span>
+}
+impl<'a> Builder<'a> {
+    /// Build the spans tree, the outer entities are added first.
+    fn spans(&mut self) -> Result<(), EntityError> {
+        // The parts are sorted in reverse, so the next part is the last one.
+        let mut parts: Vec<(usize, usize, usize)> = Vec::new();
+        for (i, e) in self.entities.iter().enumerate() {
+            if e.length() == 0 || !self.supported(e) {
+                continue;
+            }
+            let Some(end) = e.offset().checked_add(e.length()).filter(|&end| end < self.units.len()) else {
+                return Err(EntityError::new(EntityErrorType::OutOfRange, e.offset()));
+            };
+            if self.units[e.offset()].is_none() || self.units[end].is_none() {
+                return Err(EntityError::new(EntityErrorType::InvalidBoundary, e.offset()));
+            }
+            parts.push((e.offset(), end, i));
+        }
+        parts.sort_by_key(|part| std::cmp::Reverse(self.key(*part)));
+        let mut stack: Vec<usize> = vec![0];
+        while let Some((start, end, i)) = parts.pop() {
+            while stack.len() > 1 && stack.last().is_some_and(|s| self.spans[*s].end <= start) {
+                stack.pop();
+            }
+            let parent = *stack.last().unwrap_or(&0);
+            let parent_end = self.spans[parent].end;
+            if end > parent_end {
+                // The entity overlaps the end of its parent, the rest of it is a new part.
+                let rest = (parent_end, end, i);
+                let at = parts.partition_point(|part| self.key(*part) > self.key(rest));
+                parts.insert(at, rest);
+            }
+            self.spans.push(Span { entity: Some(i), start, end: end.min(parent_end), children: Vec::new() });
+            let span = self.spans.len() - 1;
+            self.spans[parent].children.push(span);
+            stack.push(span);
+        }
+        return Ok(());
+    }
+
+    /// Check if the entity has all what its node needs.
+    fn supported(&self, e: &MessageEntity) -> bool {
+        return match e.entity_type() {
+            EntityType::TextLink => e.url().is_some(),
+            EntityType::TextMention => e.user_id().is_some(),
+            EntityType::CustomEmoji => e.custom_emoji_id().is_some(),
+            _ => true
+        };
+    }
+
+    /// Get the sort key of the part, for the same range the block entities are the
+    /// outer ones and the code entities are the inner ones.
+    fn key(&self, (start, end, i): (usize, usize, usize)) -> (usize, std::cmp::Reverse<usize>, u8, usize) {
+        let rank = match self.entities[i].entity_type() {
+            EntityType::Blockquote | EntityType::ExpandableBlockquote => 0,
+            EntityType::TextLink | EntityType::TextMention => 1,
+            EntityType::Code | EntityType::Pre | EntityType::CustomEmoji => 3,
+            _ => 2
+        };
+        return (start, std::cmp::Reverse(end), rank, i);
+    }
+
+    /// Build the node of the span.
+    fn node(&self, span: usize) -> Node {
+        let Span { entity: e, start, end, children: spans } = &self.spans[span];
+        let mut children: Vec<Node> = Vec::new();
+        let mut at = *start;
+        for child in spans {
+            if self.spans[*child].start > at {
+                children.push(self.text_node(at, self.spans[*child].start));
+            }
+            children.push(self.node(*child));
+            at = self.spans[*child].end;
+        }
+        if *end > at {
+            children.push(self.text_node(at, *end));
+        }
+        let Some(e) = e.map(|i| &self.entities[i]) else {
+            let mut document = Node::new(Markdown::Document);
+            for child in children {
+                document.add_inner(child);
+            }
+            return document;
+        };
+        let position = self.position(*start);
+        let text = self.slice(*start, *end).to_string();
+        let value = match e.entity_type() {
+            EntityType::Bold => Markdown::Bold(text),
+            EntityType::Italic => Markdown::Italic(text),
+            EntityType::Underline => Markdown::Underline(text),
+            EntityType::Strikethrough => Markdown::Strikethrough(text),
+            EntityType::Spoiler => Markdown::Spoiler(text),
+            EntityType::Code => return Node::with_position(Markdown::FixedWidthCode(text), position),
+            EntityType::Pre => return Node::with_position(
+                Markdown::PreFormattedFixedWidthCode { lang: e.language().filter(|lang| !lang.is_empty()).map(str::to_string), code: text },
+                position
+            ),
+            EntityType::TextLink => Markdown::InlineURL {
+                title: text,
+                url: e.url().unwrap_or_default().to_string()
+            }.to_text_mention(),
+            EntityType::TextMention => Markdown::TextMention { name: text, user_id: e.user_id().unwrap_or_default() },
+            EntityType::CustomEmoji => return Node::with_position(
+                Markdown::CustomEmoji { emoji: text, id: e.custom_emoji_id().unwrap_or_default() },
+                position
+            ),
+            EntityType::Blockquote => Markdown::Blockquote(text),
+            EntityType::ExpandableBlockquote => Markdown::ExpandableBlockquote(text)
+        };
+        debug_assert_eq!(value.text(), visible_text(&children));
+        return entity(value, position, children);
+    }
+
+    /// Build the text node of the range.
+    fn text_node(&self, start: usize, end: usize) -> Node {
+        return Node::with_position(Markdown::Text(self.slice(start, end).to_string()), self.position(start));
+    }
+
+    /// Get the text of the range.
+    fn slice(&self, start: usize, end: usize) -> &'a str {
+        return &self.text[self.byte(start)..self.byte(end)];
+    }
+
+    /// Get the position of the UTF-16 code unit.
+    fn position(&self, unit: usize) -> Position {
+        return Position::default().advance_str(&self.text[..self.byte(unit)]);
+    }
+
+    /// Get the byte index of the UTF-16 code unit, the spans never start or end
+    /// inside a surrogate pair.
+    fn byte(&self, unit: usize) -> usize {
+        return self.units[unit].unwrap_or_default();
+    }
+}
+
+/// The message entities import tests.
+#[cfg(test)]
+mod entities_parser_tests {
+    use super::{from_json, parse_json, parser, EntityErrorType, MAX_DEPTH};
+    use crate::parser::{html, mdv2};
+    use crate::parser::enums::{Markdown, MarkdownErrorType};
+    use crate::formatted::{EntityType, MessageEntity};
+    use crate::render::{self, entities};
+
+    #[test]
+    fn export_round_trip() {
+        for document in [
+            "😀 *bold _italic_ bold* __u__ ~s~ ||p|| `code` ```rust\nlet x;```",
+            "[a *b*](https://a.com) [m](tg://user?id=7) ![👍](tg://emoji?id=5)",
+            ">quote *bold\n>line*\n**>shown\n>hidden||",
+        ] {
+            let ast = mdv2::parser(document).unwrap();
+            let (text, list) = entities::entities(&ast);
+            assert_eq!(parser(&text, &list).unwrap(), ast, "in {:?}", document);
+            let json = entities::to_json(&list);
+            assert_eq!(parser(&text, &from_json(&json).unwrap()).unwrap(), ast, "in {:?}", document);
+        }
+    }
+
+    #[test]
+    fn overlapping() {
+        // The same range, the link is outside and the code is inside.
+        let ast = parser("ab cd", &[
+            MessageEntity::new(EntityType::Code, 0, 2),
+            MessageEntity::new(EntityType::Bold, 0, 2),
+            MessageEntity::new(EntityType::TextLink, 0, 2).with_url("https://a.com"),
+            MessageEntity::new(EntityType::Italic, 1, 4),
+            MessageEntity::new(EntityType::Underline, 4, 1),
+        ]).unwrap();
+        assert_eq!(
+            ast,
+            html::parser("<a href=\"https://a.com\"><b><code>ab</code></b></a><i> c<u>d</u></i>").unwrap()
+        );
+        assert_eq!(ast.inner()[1].value(), &Markdown::Italic(" cd".to_string()));
+        assert_eq!(ast.inner()[1].position().char(), 2);
+    }
+
+    #[test]
+    fn markdown_output() {
+        // The trees that the Markdown can not write are refused, not rendered to a broken source.
+        let ast = parser("a (b", &[MessageEntity::new(EntityType::Bold, 0, 1)]).unwrap();
+        let err = render::mdv1::serialize(&ast).unwrap_err();
+        assert_eq!(err.err(), &MarkdownErrorType::ParenthesesOpen);
+        let ast = parser("ab", &[
+            MessageEntity::new(EntityType::Italic, 0, 1),
+            MessageEntity::new(EntityType::Underline, 1, 1),
+        ]).unwrap();
+        let source = render::mdv2::serialize(&ast).unwrap();
+        assert_eq!(mdv2::parser(&source).unwrap(), ast);
+    }
+
+    #[test]
+    fn range_errors() {
+        let err = parser("ab", &[MessageEntity::new(EntityType::Bold, 1, 2)]).unwrap_err();
+        assert_eq!((err.err(), err.offset()), (&EntityErrorType::OutOfRange, 1));
+        let err = parser("a😀", &[MessageEntity::new(EntityType::Bold, 0, 2)]).unwrap_err();
+        assert_eq!((err.err(), err.offset()), (&EntityErrorType::InvalidBoundary, 0));
+        let list = from_json("[{\"type\":\"bold\",\"offset\":18446744073709551615,\"length\":1}]").unwrap();
+        let err = parser("ab", &list).unwrap_err();
+        assert_eq!((err.err(), err.offset()), (&EntityErrorType::OutOfRange, usize::MAX));
+    }
+
+    #[test]
+    fn empty_language() {
+        let ast = parser("x", &[MessageEntity::new(EntityType::Pre, 0, 1).with_language("")]).unwrap();
+        assert_eq!(ast.inner()[0].value(), &Markdown::PreFormattedFixedWidthCode { lang: None, code: "x".to_string() });
+        assert_eq!(mdv2::parser(&render::mdv2::serialize(&ast).unwrap()).unwrap(), ast);
+    }
+
+    #[test]
+    fn json() {
+        let ast = parse_json(concat!(
+            "{\"message_id\": 1, \"text\": \"\\ud83d\\ude00 hi \\\"you\\\"\",",
+            " \"entities\": [{\"type\": \"text_mention\", \"offset\": 3, \"length\": 8,",
+            " \"user\": {\"id\": 7, \"is_bot\": false, \"first_name\": \"A\"}}]}"
+        ), "text").unwrap();
+        assert_eq!(
+            ast.inner()[1].value(),
+            &Markdown::TextMention { name: "hi \"you\"".to_string(), user_id: 7 }
+        );
+        for (json, err, offset) in [
+            ("[{\"type\": \"bold\", \"offset\": 0, \"length\": 1},]", EntityErrorType::InvalidJSON, 44),
+            ("[{\"type\": \"bold\", \"offset\": -1, \"length\": 1}]", EntityErrorType::InvalidField("offset".to_string()), 28),
+            ("[{\"type\": \"text_link\", \"offset\": 0, \"length\": 1}]", EntityErrorType::MissingField("url".to_string()), 1),
+            ("{\"entities\": []}", EntityErrorType::InvalidField("entities".to_string()), 0),
+        ] {
+            let error = from_json(json).unwrap_err();
+            assert_eq!((error.err(), error.offset()), (&err, offset), "in {:?}", json);
+        }
+        let err = parse_json("{\"caption\": \"a\"}", "text").unwrap_err();
+        assert_eq!(err.err(), &EntityErrorType::MissingField("text".to_string()));
+    }
+
+    #[test]
+    fn json_depth() {
+        let deep = "[".repeat(200000) + &"]".repeat(200000);
+        let err = from_json(&deep).unwrap_err();
+        assert_eq!((err.err(), err.offset()), (&EntityErrorType::InvalidJSON, MAX_DEPTH));
+        let nested = "[".repeat(MAX_DEPTH) + &"]".repeat(MAX_DEPTH);
+        assert_eq!(from_json(&nested).unwrap_err().err(), &EntityErrorType::InvalidField("entities".to_string()));
+    }
+}
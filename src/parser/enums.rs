@@ -1,4 +1,6 @@
+use std::fmt;
 
+use super::url::{self, URLErrorType};
 
 /// The Markdown types.
 #[derive(Debug, Clone, PartialEq)]
@@ -11,6 +13,12 @@ pub enum Markdown {
     Bold(String),
     /// A Italic text. for example: `_italic text_`.
     Italic(String),
+    /// An Underline text, for example: `__underline text__`.
+    Underline(String),
+    /// A Strikethrough text, for example: `~strikethrough text~`.
+    Strikethrough(String),
+    /// A Spoiler text, for example: `||spoiler text||`.
+    Spoiler(String),
     /// A Fixed Width Code, for example: \`fixed code\`.
     FixedWidthCode(String),
     /// A Pre-Fixed Width Code, for example: \`\`\`Pre-Fixed Width Code\`\`\`.
@@ -27,16 +35,142 @@ pub enum Markdown {
         /// The URL, for example: `https://example.com`.
         url: String
     },
+    /// A Text Mention, It is an Inline URL to a user, for example: `[name](tg://user?id=123456)`.
+    TextMention {
+        /// The mention text, for example: `name`.
+        name: String,
+        /// The user id, for example: `123456`.
+        user_id: u64
+    },
+    /// A Custom Emoji, for example: `![👍](tg://emoji?id=5368324170671202286)`.
+    CustomEmoji {
+        /// The alternative emoji, for example: `👍`.
+        emoji: String,
+        /// The custom emoji id, for example: `5368324170671202286`.
+        id: u64
+    },
+    /// A Blockquote, It is a block of lines that start with `>`, for example: `>quote`.
+    Blockquote(String),
+    /// An Expandable Blockquote, It starts with `**>` and its last line ends with `||`, 
+    /// for example: `**>quote\n>hidden||`.
+    ExpandableBlockquote(String),
+    /// A broken region that could not be parsed, It holds the region source, 
+    /// for example: the `*` in `*not closed`.
+    Error(String),
+}
+impl Markdown {
+    /// Get the visible text of the value, for the Pre-Fixed Width Code it is the code
+    /// without the language.
+    /// 
+    /// ### Example:
+    /// ```
+    /// use telemark::parser::enums::Markdown;
+    /// 
+    /// assert_eq!(Markdown::Bold("bold".to_string()).text(), "bold");
+    /// assert_eq!(
+    ///     Markdown::PreFormattedFixedWidthCode { lang: Some("rust".to_string()), code: "let x;".to_string() }.text(),
+    ///     "let x;"
+    /// );
+    /// ```
+    pub fn text(&self) -> &str {
+        return match self {
+            Markdown::Document => "",
+            Markdown::Text(text) => text,
+            Markdown::Bold(text) => text,
+            Markdown::Italic(text) => text,
+            Markdown::Underline(text) => text,
+            Markdown::Strikethrough(text) => text,
+            Markdown::Spoiler(text) => text,
+            Markdown::FixedWidthCode(code) => code,
+            Markdown::PreFormattedFixedWidthCode { code, .. } => code,
+            Markdown::InlineURL { title, .. } => title,
+            Markdown::TextMention { name, .. } => name,
+            Markdown::CustomEmoji { emoji, .. } => emoji,
+            Markdown::Blockquote(text) => text,
+            Markdown::ExpandableBlockquote(text) => text,
+            Markdown::Error(source) => source
+        };
+    }
+
+    /// Get the URL of the Inline URL or the Text Mention.
+    /// 
+    /// ### Example:
+    /// ```
+    /// use telemark::parser::enums::Markdown;
+    /// 
+    /// let mention = Markdown::TextMention { name: "name".to_string(), user_id: 123456 };
+    /// assert_eq!(mention.url(), Some("tg://user?id=123456".to_string()));
+    /// assert_eq!(Markdown::Text("text".to_string()).url(), None);
+    /// ```
+    pub fn url(&self) -> Option<String> {
+        return match self {
+            Markdown::InlineURL { url, .. } => Some(url.clone()),
+            Markdown::TextMention { user_id, .. } => Some(format!("tg://user?id={}", user_id)),
+            _ => None
+        };
+    }
+
+    /// Convert the Text Mention to its Inline URL form, the other values are returned as they are.
+    /// 
+    /// ### Example:
+    /// ```
+    /// use telemark::parser::enums::Markdown;
+    /// 
+    /// let mention = Markdown::TextMention { name: "name".to_string(), user_id: 123456 };
+    /// assert_eq!(
+    ///     mention.to_inline_url(),
+    ///     Markdown::InlineURL { title: "name".to_string(), url: "tg://user?id=123456".to_string() }
+    /// );
+    /// ```
+    pub fn to_inline_url(&self) -> Markdown {
+        return match self {
+            Markdown::TextMention { name, .. } => Markdown::InlineURL {
+                title: name.clone(),
+                url: self.url().unwrap_or_default()
+            },
+            _ => self.clone()
+        };
+    }
+
+    /// Convert the Inline URL to a Text Mention if its URL is `tg://user?id=`, 
+    /// the other values are returned as they are.
+    /// 
+    /// ### Example:
+    /// ```
+    /// use telemark::parser::enums::Markdown;
+    /// 
+    /// let url = Markdown::InlineURL { title: "name".to_string(), url: "tg://user?id=7".to_string() };
+    /// assert_eq!(
+    ///     url.to_text_mention(),
+    ///     Markdown::TextMention { name: "name".to_string(), user_id: 7 }
+    /// );
+    /// ```
+    pub fn to_text_mention(&self) -> Markdown {
+        if let Markdown::InlineURL { title, url } = self 
+            && let Some(user_id) = url::text_mention_id(url) {
+            return Markdown::TextMention {
+                name: title.clone(),
+                user_id
+            };
+        }
+        return self.clone();
+    }
 }
 
 
 /// The Markdown errors.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum MarkdownErrorType {
     /// Star (`*`) is opne.
     StarOpen,
     /// Underscore (`_`) is opne.
     UnderscoreOpen,
+    /// Double Underscore (`__`) is open.
+    DoubleUnderscoreOpen,
+    /// Tilde (`~`) is open.
+    TildeOpen,
+    /// Double Pipe (`||`) is open.
+    DoublePipeOpen,
     /// Backtick (\`) is opne.
     BacktickOpen,
     /// Backticks (\`\`\`) is opne.
@@ -44,5 +178,143 @@ pub enum MarkdownErrorType {
     /// Square Brackets (`[`) is opne.
     SquareBracketsOpen,
     /// Parentheses (`(`) is opne.
-    ParenthesesOpen
-}
\ No newline at end of file
+    ParenthesesOpen,
+    /// The Inline URL parentheses (`[title](`) is open.
+    InlineURLOpen,
+    /// An entity without text, for example: `**` or `[](https://example.com)`.
+    EmptyEntity,
+    /// The Inline URL is empty or whitespace, for example: `[title]( )`.
+    EmptyURL,
+    /// A formatting token in the Inline URL title, for example: `[*title*](https://example.com)`.
+    FormattingInURLTitle,
+    /// The Inline URL is not valid, for example: `[title](ftp://example.com)`.
+    InvalidURL(URLErrorType),
+    /// An Inline URL inside an Inline URL title, for example: `[a [b](https://b.com)](https://a.com)`.
+    NestedInlineURL,
+    /// A reserved character that is not escaped in Markdown V2, for example: the `.` in `end.`.
+    UnescapedCharacter(char),
+    /// A Blockquote that does not start at the start of a line, for example: `text **>quote`.
+    BlockquoteNotAtLineStart,
+    /// A Blockquote that is not followed by a new line, the text after it would be a part of
+    /// its last line, for example: a blockquote that is followed by a bold.
+    BlockquoteNotAtLineEnd,
+    /// A Blockquote inside another entity, for example: `*bold\n>quote*`.
+    BlockquoteInEntity,
+    /// The Expandable Blockquote (`**>`) is open, its last line must end with `||`.
+    ExpandableBlockquoteOpen,
+    /// The HTML tag is not closed, for example: `<b>bold`.
+    UnclosedTag(String),
+    /// The HTML closing tag does not close the last open tag, for example: the `</b>` in `<b><i>text</b>`.
+    MismatchedTag {
+        /// The expected closing tag, it is `None` if no tag is open.
+        expected: Option<String>,
+        /// The found closing tag.
+        found: String
+    },
+    /// The HTML tag is not supported by Telegram, for example: `<div>`.
+    UnsupportedTag(String),
+    /// The HTML tag is not well formed, for example: `<a href="https://example.com"`.
+    InvalidTag,
+    /// An HTML tag inside a code, for example: `<code><b>code</b></code>`.
+    TagInCode(String),
+    /// The HTML tag has no required attribute, for example: `<a>link</a>`.
+    MissingAttribute(String),
+    /// The HTML attribute value is not valid, for example: `<tg-emoji emoji-id="x">`.
+    InvalidAttribute(String),
+    /// The HTML entity is not supported by Telegram, for example: `&nbsp;`.
+    UnknownEntity(String),
+    /// The entity is not supported by the parse mode, for example: an underline in Markdown V1.
+    UnsupportedEntity(String),
+    /// An entity inside another entity in a parse mode without nesting, for example: an italic
+    /// inside a bold in Markdown V1.
+    NestedEntity,
+    /// A character that can not be written in the parse mode, for example: the `\` in Markdown V1.
+    UnrepresentableCharacter(char)
+}
+impl MarkdownErrorType {
+    /// Get the closing delimiter that was expected, if the error is an unclosed entity.
+    /// 
+    /// ### Example:
+    /// ```
+    /// use telemark::parser::enums::MarkdownErrorType;
+    /// 
+    /// assert_eq!(MarkdownErrorType::SquareBracketsOpen.closer(), Some("]"));
+    /// ```
+    pub fn closer(&self) -> Option<&'static str> {
+        return match self {
+            MarkdownErrorType::StarOpen => Some("*"),
+            MarkdownErrorType::UnderscoreOpen => Some("_"),
+            MarkdownErrorType::DoubleUnderscoreOpen => Some("__"),
+            MarkdownErrorType::TildeOpen => Some("~"),
+            MarkdownErrorType::DoublePipeOpen => Some("||"),
+            MarkdownErrorType::BacktickOpen => Some("`"),
+            MarkdownErrorType::BackticksOpen => Some("```"),
+            MarkdownErrorType::SquareBracketsOpen => Some("]"),
+            MarkdownErrorType::ParenthesesOpen => Some(")"),
+            MarkdownErrorType::InlineURLOpen => Some(")"),
+            MarkdownErrorType::EmptyEntity => None,
+            MarkdownErrorType::EmptyURL => None,
+            MarkdownErrorType::FormattingInURLTitle => None,
+            MarkdownErrorType::InvalidURL(_) => None,
+            MarkdownErrorType::NestedInlineURL => None,
+            MarkdownErrorType::UnescapedCharacter(_) => None,
+            MarkdownErrorType::BlockquoteNotAtLineStart => None,
+            MarkdownErrorType::BlockquoteNotAtLineEnd => None,
+            MarkdownErrorType::BlockquoteInEntity => None,
+            MarkdownErrorType::ExpandableBlockquoteOpen => Some("||"),
+            MarkdownErrorType::UnclosedTag(_) => None,
+            MarkdownErrorType::MismatchedTag { .. } => None,
+            MarkdownErrorType::UnsupportedTag(_) => None,
+            MarkdownErrorType::InvalidTag => None,
+            MarkdownErrorType::TagInCode(_) => None,
+            MarkdownErrorType::MissingAttribute(_) => None,
+            MarkdownErrorType::InvalidAttribute(_) => None,
+            MarkdownErrorType::UnknownEntity(_) => None,
+            MarkdownErrorType::UnsupportedEntity(_) => None,
+            MarkdownErrorType::NestedEntity => None,
+            MarkdownErrorType::UnrepresentableCharacter(_) => None
+        };
+    }
+}
+impl fmt::Display for MarkdownErrorType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            MarkdownErrorType::StarOpen => write!(f, "unclosed bold, the star `*` is open"),
+            MarkdownErrorType::UnderscoreOpen => write!(f, "unclosed italic, the underscore `_` is open"),
+            MarkdownErrorType::DoubleUnderscoreOpen => write!(f, "unclosed underline, the double underscore `__` is open"),
+            MarkdownErrorType::TildeOpen => write!(f, "unclosed strikethrough, the tilde `~` is open"),
+            MarkdownErrorType::DoublePipeOpen => write!(f, "unclosed spoiler, the double pipe `||` is open"),
+            MarkdownErrorType::BacktickOpen => write!(f, "unclosed fixed width code, the backtick `` ` `` is open"),
+            MarkdownErrorType::BackticksOpen => write!(f, "unclosed pre-formatted code, the backticks ``` is open"),
+            MarkdownErrorType::SquareBracketsOpen => write!(f, "unclosed URL title, the square bracket `[` is open"),
+            MarkdownErrorType::ParenthesesOpen => write!(f, "unclosed parentheses, the parenthesis `(` is open"),
+            MarkdownErrorType::InlineURLOpen => write!(f, "unclosed inline URL, the parenthesis `(` after the title is open"),
+            MarkdownErrorType::EmptyEntity => write!(f, "empty entity, the entity has no text"),
+            MarkdownErrorType::EmptyURL => write!(f, "empty inline URL, the URL is empty or whitespace"),
+            MarkdownErrorType::FormattingInURLTitle => write!(f, "formatting is not allowed in the inline URL title"),
+            MarkdownErrorType::InvalidURL(err) => write!(f, "invalid inline URL, {}", err),
+            MarkdownErrorType::NestedInlineURL => write!(f, "an inline URL can not contain another inline URL"),
+            MarkdownErrorType::UnescapedCharacter(c) => write!(f, "the reserved character '{}' must be escaped", c),
+            MarkdownErrorType::BlockquoteNotAtLineStart => write!(f, "a blockquote must start at the start of a line"),
+            MarkdownErrorType::BlockquoteNotAtLineEnd => write!(f, "a blockquote must end at the end of a line"),
+            MarkdownErrorType::BlockquoteInEntity => write!(f, "a blockquote can not be inside another entity"),
+            MarkdownErrorType::ExpandableBlockquoteOpen => write!(f, "unclosed expandable blockquote, its last line must end with `||`"),
+            MarkdownErrorType::UnclosedTag(tag) => write!(f, "unclosed tag, the tag `<{}>` is open", tag),
+            MarkdownErrorType::MismatchedTag { expected: Some(expected), found } => write!(
+                f, "mismatched closing tag `</{}>`, expected `</{}>`", found, expected
+            ),
+            MarkdownErrorType::MismatchedTag { expected: None, found } => write!(
+                f, "the closing tag `</{}>` has no opening tag", found
+            ),
+            MarkdownErrorType::UnsupportedTag(tag) => write!(f, "the tag `<{}>` is not supported", tag),
+            MarkdownErrorType::InvalidTag => write!(f, "the tag is not well formed"),
+            MarkdownErrorType::TagInCode(tag) => write!(f, "the tag `<{}>` is not allowed inside code", tag),
+            MarkdownErrorType::MissingAttribute(attribute) => write!(f, "the tag has no `{}` attribute", attribute),
+            MarkdownErrorType::InvalidAttribute(attribute) => write!(f, "the `{}` attribute value is not valid", attribute),
+            MarkdownErrorType::UnknownEntity(entity) => write!(f, "the HTML entity `{}` is not supported", entity),
+            MarkdownErrorType::UnsupportedEntity(entity) => write!(f, "the {} entity is not supported in this parse mode", entity),
+            MarkdownErrorType::NestedEntity => write!(f, "the entities can not be nested in this parse mode"),
+            MarkdownErrorType::UnrepresentableCharacter(c) => write!(f, "the character '{}' can not be written in this parse mode", c)
+        };
+    }
+}
@@ -0,0 +1,697 @@
+use crate::lexer::types::Position;
+use crate::reader::CharReader;
+
+use super::types::{entity, visible_text, MarkdownError, Node};
+use super::enums::{Markdown, MarkdownErrorType};
+use super::url;
+
+/// The HTML tags that Telegram supports.
+pub const TAGS: [&str; 16] = [
+    "b", "strong", "i", "em", "u", "ins", "s", "strike", "del",
+    "tg-spoiler", "span", "a", "code", "pre", "blockquote", "tg-emoji"
+];
+
+/// The named HTML entities that Telegram supports.
+pub const ENTITIES: [(&str, char); 4] = [("lt", '<'), ("gt", '>'), ("amp", '&'), ("quot", '"')];
+
+/// The HTML parser, It parses the Telegram HTML subset to the same AST of the
+/// Markdown parsers, It stops on the first error.
+/// 
+/// ### Example:
+/// ```
+/// use telemark::parser::html;
+/// use telemark::parser::enums::{Markdown, MarkdownErrorType};
+/// 
+/// let ast = html::parser("<b>bold <i>italic bold</i></b>").unwrap();
+/// let bold = &ast.inner()[0];
+/// assert_eq!(bold.value(), &Markdown::Bold("bold italic bold".to_string()));
+/// assert_eq!(bold.inner()[1].value(), &Markdown::Italic("italic bold".to_string()));
+/// 
+/// let err = html::parser("<b><i>text</b></i>").unwrap_err();
+/// assert_eq!(
+///     err.err(),
+///     &MarkdownErrorType::MismatchedTag { expected: Some("i".to_string()), found: "b".to_string() }
+/// );
+/// assert_eq!(err.offset(), 10);
+/// 
+/// // The HTML entities are decoded, and the bare `&` and `<` are errors.
+/// let ast = html::parser("a &lt; b").unwrap();
+/// assert_eq!(ast.inner()[0].value(), &Markdown::Text("a < b".to_string()));
+/// let err = html::parser("a < b").unwrap_err();
+/// assert_eq!(err.err(), &MarkdownErrorType::UnescapedCharacter('<'));
+/// ```
+pub fn parser(document: &str) -> Result<Node, MarkdownError> {
+    let mut parser = Parser::new(document, false);
+    let node = parser.parse();
+    return match parser.errors.into_iter().next() {
+        Some(err) => Err(err),
+        None => Ok(node)
+    };
+}
+
+/// The HTML parser with error recovery, It collects all the errors in the
+/// document instead of stopping at the first one. After an unclosed tag it
+/// continues parsing right after the opening tag, and the broken region is
+/// kept in the AST as a `Markdown::Error` node.
+/// 
+/// ### Example:
+/// ```
+/// use telemark::parser::html;
+/// use telemark::parser::enums::{Markdown, MarkdownErrorType};
+/// 
+/// let (ast, errors) = html::parse_all("<div>a</div> <b>b");
+/// assert_eq!(errors.len(), 3);
+/// assert_eq!(errors[0].err(), &MarkdownErrorType::UnsupportedTag("div".to_string()));
+/// assert_eq!(errors[2].err(), &MarkdownErrorType::UnclosedTag("b".to_string()));
+/// assert_eq!(ast.inner()[1].value(), &Markdown::Text("a".to_string()));
+/// ```
+pub fn parse_all(document: &str) -> (Node, Vec<MarkdownError>) {
+    let mut parser = Parser::new(document, true);
+    let node = parser.parse();
+    let mut errors = parser.errors;
+    errors.sort_by_key(|err| err.position().byte());
+    return (node, errors);
+}
+
+/// The HTML tag attribute.
+pub(crate) struct Attribute<'a> {
+    /// The lowercase attribute name.
+    pub(crate) name: String,
+    /// The raw attribute value, It is empty if the attribute has no value.
+    pub(crate) value: &'a str,
+    /// The position of the value.
+    pub(crate) position: Position
+}
+
+/// The HTML tag.
+pub(crate) struct Tag<'a> {
+    /// The lowercase tag name.
+    pub(crate) name: String,
+    pub(crate) closing: bool,
+    pub(crate) attributes: Vec<Attribute<'a>>,
+    /// The position of the `<`.
+    pub(crate) start: Position,
+    /// The position after the `>`.
+    pub(crate) end: Position
+}
+impl<'a> Tag<'a> {
+    /// Get the attribute by its name.
+    pub(crate) fn attribute(&self, name: &str) -> Option<&Attribute<'a>> {
+        return self.attributes.iter().find(|a| a.name == name);
+    }
+}
+
+/// The HTML tag reader, It reads the document chars and the tags.
+pub(crate) struct TagReader<'a> {
+    document: &'a str,
+    chars: CharReader<'a>
+}
+impl<'a> TagReader<'a> {
+    /// Create new `TagReader` that starts from `position`.
+    pub(crate) fn from_position(document: &'a str, position: Position) -> Self {
+        return Self {
+            document,
+            chars: CharReader::from_position(document, position)
+        };
+    }
+
+    /// Get the next char.
+    pub(crate) fn next_char(&mut self) -> Option<char> {
+        return self.chars.next_char();
+    }
+
+    /// Get the next char without consuming it.
+    pub(crate) fn peek_char(&self) -> Option<char> {
+        return self.chars.peek_char();
+    }
+
+    /// Get the position of the next char.
+    pub(crate) fn next_position(&self) -> Position {
+        return self.chars.next_position();
+    }
+
+    /// Check if the reader is at the start of a tag, It is `<` followed by a letter or `/`.
+    pub(crate) fn tag_start(&self) -> bool {
+        let Some(s) = self.chars.get_string(2) else {
+            return false;
+        };
+        let mut chars = s.chars();
+        return chars.next() == Some('<')
+            && chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '/');
+    }
+
+    /// Read the tag, It returns `None` if the tag is not well formed.
+    pub(crate) fn tag(&mut self) -> Option<Tag<'a>> {
+        let start = self.chars.next_position();
+        self.chars.next_char();
+        let closing = self.chars.peek_char() == Some('/');
+        if closing {
+            self.chars.next_char();
+        }
+        let name = self.name()?.to_ascii_lowercase();
+        let mut attributes: Vec<Attribute<'a>> = Vec::new();
+        loop {
+            self.spaces();
+            match self.chars.peek_char()? {
+                '>' => break,
+                '/' => {
+                    // The self-closing tag, for example: `<br/>`.
+                    self.chars.next_char();
+                    if self.chars.peek_char()? != '>' {
+                        return None;
+                    }
+                    break;
+                },
+                _ if closing => return None,
+                _ => attributes.push(self.attribute()?)
+            }
+        }
+        self.chars.next_char();
+        return Some(Tag {
+            name,
+            closing,
+            attributes,
+            start,
+            end: self.chars.next_position()
+        });
+    }
+
+    /// Read the tag or the attribute name, It starts with a letter.
+    fn name(&mut self) -> Option<&'a str> {
+        let start = self.chars.next_position();
+        if !self.chars.peek_char()?.is_ascii_alphabetic() {
+            return None;
+        }
+        while self.chars.peek_char().is_some_and(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            self.chars.next_char();
+        }
+        return Some(&self.document[start.byte()..self.chars.next_position().byte()]);
+    }
+
+    /// Read the attribute, the value can be quoted, unquoted or missing.
+    fn attribute(&mut self) -> Option<Attribute<'a>> {
+        let name = self.name()?.to_ascii_lowercase();
+        self.spaces();
+        if self.chars.peek_char() != Some('=') {
+            return Some(Attribute { name, value: "", position: self.chars.next_position() });
+        }
+        self.chars.next_char();
+        self.spaces();
+        let quote = self.chars.peek_char()?;
+        if quote == '"' || quote == '\'' {
+            self.chars.next_char();
+            let position = self.chars.next_position();
+            while self.chars.peek_char()? != quote {
+                self.chars.next_char();
+            }
+            let value = &self.document[position.byte()..self.chars.next_position().byte()];
+            self.chars.next_char();
+            return Some(Attribute { name, value, position });
+        }
+        let position = self.chars.next_position();
+        while self.chars.peek_char().is_some_and(|c| !c.is_whitespace() && c != '>') {
+            self.chars.next_char();
+        }
+        let value = &self.document[position.byte()..self.chars.next_position().byte()];
+        if value.is_empty() {
+            return None;
+        }
+        return Some(Attribute { name, value, position });
+    }
+
+    /// Skip the whitespaces.
+    fn spaces(&mut self) {
+        while self.chars.peek_char().is_some_and(|c| c.is_whitespace()) {
+            self.chars.next_char();
+        }
+    }
+}
+
+/// An open tag, It waits for its closing tag.
+struct Frame<'a> {
+    /// The opening tag, the document frame has no tag.
+    tag: Option<Tag<'a>>,
+    /// The language of the Pre-Fixed Width Code.
+    lang: Option<String>,
+    /// The parsed inner nodes.
+    children: Vec<Node>
+}
+impl<'a> Frame<'a> {
+    /// Get the tag name, the document frame has an empty name.
+    fn name(&self) -> &str {
+        return self.tag.as_ref().map(|t| t.name.as_str()).unwrap_or("");
+    }
+}
+
+/// The HTML parser state.
+struct Parser<'a> {
+    document: &'a str,
+    reader: TagReader<'a>,
+    recover: bool,
+    errors: Vec<MarkdownError>,
+    stack: Vec<Frame<'a>>
+}
+impl<'a> Parser<'a> {
+    /// Create new `Parser`, if `recover` is false it will stop on the first error.
+    fn new(document: &'a str, recover: bool) -> Self {
+        return Self {
+            document,
+            reader: TagReader::from_position(document, Position::default()),
+            recover,
+            errors: Vec::new(),
+            stack: Vec::new()
+        };
+    }
+
+    /// Parse the document to an AST.
+    fn parse(&mut self) -> Node {
+        self.stack = vec![Frame { tag: None, lang: None, children: Vec::new() }];
+        loop {
+            if !self.recover && !self.errors.is_empty() {
+                break;
+            }
+            if self.reader.peek_char().is_none() {
+                if self.stack.len() > 1 {
+                    // The outer open tag is reported first.
+                    self.unclosed(1);
+                    continue;
+                }
+                break;
+            }
+            if !self.reader.tag_start() {
+                self.text();
+                continue;
+            }
+            let start = self.reader.next_position();
+            match self.reader.tag() {
+                Some(tag) if tag.closing => self.close(tag),
+                Some(tag) => self.open(tag),
+                None => {
+                    let end = start.advance('<');
+                    self.invalid(MarkdownErrorType::InvalidTag, start, start, end);
+                    self.reader = TagReader::from_position(self.document, end);
+                }
+            }
+        }
+        let mut node = Node::new(Markdown::Document);
+        for child in self.stack.pop().map(|f| f.children).unwrap_or_default() {
+            node.add_inner(child);
+        }
+        return node;
+    }
+
+    /// Read the text until the next tag, the HTML entities are decoded.
+    fn text(&mut self) {
+        let start = self.reader.next_position();
+        self.reader.next_char();
+        while self.reader.peek_char().is_some() && !self.reader.tag_start() {
+            self.reader.next_char();
+        }
+        let end = self.reader.next_position();
+        let text = self.decode(&self.document[start.byte()..end.byte()], start);
+        self.add(Node::with_position(Markdown::Text(text), start));
+    }
+
+    /// Decode the HTML entities of the raw text that starts at `position`. The unknown
+    /// entities and the bare `&` and `<` are reported and kept as they are.
+    fn decode(&mut self, raw: &str, position: Position) -> String {
+        let mut value = String::new();
+        let mut position = position;
+        let mut rest = raw;
+        while let Some(c) = rest.chars().next() {
+            let len = match (c, reference(rest)) {
+                ('&', Some((Some(decoded), len))) => {
+                    value.push(decoded);
+                    len
+                },
+                ('&', Some((None, len))) => {
+                    let err = MarkdownErrorType::UnknownEntity(rest[..len].to_string());
+                    self.report(MarkdownError::with_position(err, position));
+                    value += &rest[..len];
+                    len
+                },
+                ('&' | '<', None) => {
+                    self.report(MarkdownError::with_position(MarkdownErrorType::UnescapedCharacter(c), position));
+                    value.push(c);
+                    1
+                },
+                _ => {
+                    value.push(c);
+                    c.len_utf8()
+                }
+            };
+            position = position.advance_str(&rest[..len]);
+            rest = &rest[len..];
+        }
+        return value;
+    }
+
+    /// Open the tag, nothing can be inside the code except the code of the pre,
+    /// and the links and the blockquotes can not be nested.
+    fn open(&mut self, tag: Tag<'a>) {
+        let (start, end) = (tag.start, tag.end);
+        if !TAGS.contains(&tag.name.as_str()) {
+            return self.invalid(MarkdownErrorType::UnsupportedTag(tag.name), start, start, end);
+        }
+        if tag.name == "span" {
+            match tag.attribute("class") {
+                Some(class) if class.value == "tg-spoiler" => (),
+                Some(_) => return self.invalid(MarkdownErrorType::InvalidAttribute("class".to_string()), start, start, end),
+                None => return self.invalid(MarkdownErrorType::MissingAttribute("class".to_string()), start, start, end)
+            }
+        }
+        let top = self.stack.last();
+        let pre_code = tag.name == "code"
+            && top.is_some_and(|f| f.name() == "pre" && f.children.is_empty() && f.lang.is_none());
+        if top.is_some_and(|f| f.name() == "code" || f.name() == "pre") && !pre_code {
+            return self.invalid(MarkdownErrorType::TagInCode(tag.name), start, start, end);
+        }
+        if tag.name == "a" && self.stack.iter().any(|f| f.name() == "a") {
+            return self.invalid(MarkdownErrorType::NestedInlineURL, start, start, end);
+        }
+        if tag.name == "blockquote" && self.stack.len() > 1 {
+            return self.invalid(MarkdownErrorType::BlockquoteInEntity, start, start, end);
+        }
+        self.stack.push(Frame { tag: Some(tag), lang: None, children: Vec::new() });
+    }
+
+    /// Close the last open tag and add its node to the parent.
+    fn close(&mut self, close: Tag<'a>) {
+        let (start, end) = (close.start, close.end);
+        if !TAGS.contains(&close.name.as_str()) {
+            return self.invalid(MarkdownErrorType::UnsupportedTag(close.name), start, start, end);
+        }
+        if self.stack.len() == 1 || self.stack.last().is_some_and(|f| f.name() != close.name) {
+            let expected = if self.stack.len() > 1 {
+                self.stack.last().map(|f| f.name().to_string())
+            } else {
+                None
+            };
+            let open = self.stack.iter().rposition(|f| f.name() == close.name);
+            let err = MarkdownErrorType::MismatchedTag { expected, found: close.name };
+            return match open {
+                // The inner tags must be closed before their parent.
+                Some(i) => self.fail(i + 1, MarkdownError::with_position(err, start)),
+                None => self.invalid(err, start, start, end)
+            };
+        }
+        let Some(Frame { tag: Some(open), lang, children }) = self.stack.pop() else {
+            return;
+        };
+        let text = visible_text(&children);
+        let value = match open.name.as_str() {
+            "b" | "strong" => Markdown::Bold(text),
+            "i" | "em" => Markdown::Italic(text),
+            "u" | "ins" => Markdown::Underline(text),
+            "s" | "strike" | "del" => Markdown::Strikethrough(text),
+            "tg-spoiler" | "span" => Markdown::Spoiler(text),
+            "code" if self.stack.last().is_some_and(|f| f.name() == "pre") => {
+                // `<pre><code class="language-rust">code</code></pre>`
+                let class = match open.attribute("class") {
+                    Some(class) => self.decode(class.value, class.position),
+                    None => String::new()
+                };
+                if let Some(pre) = self.stack.last_mut() {
+                    pre.lang = class.strip_prefix("language-")
+                        .filter(|lang| !lang.is_empty())
+                        .map(|lang| lang.to_string());
+                }
+                self.add(Node::with_position(Markdown::Text(text), open.start));
+                return;
+            },
+            "code" => Markdown::FixedWidthCode(text),
+            "pre" => Markdown::PreFormattedFixedWidthCode { lang, code: text },
+            "blockquote" if open.attribute("expandable").is_some() => Markdown::ExpandableBlockquote(text),
+            "blockquote" => Markdown::Blockquote(text),
+            "a" => {
+                let href = open.attribute("href").map(|href| (self.decode(href.value, href.position), href));
+                let err = match &href {
+                    _ if text.is_empty() => Some((MarkdownErrorType::EmptyEntity, open.start)),
+                    None => Some((MarkdownErrorType::MissingAttribute("href".to_string()), open.start)),
+                    Some((url, href)) if url.trim().is_empty() => Some((MarkdownErrorType::EmptyURL, href.position)),
+                    Some((url, href)) => url::validate(url).err().map(|err| (
+                        MarkdownErrorType::InvalidURL(err.err().clone()),
+                        source_position(href.value, href.position, err.offset())
+                    ))
+                };
+                if let Some((err, position)) = err {
+                    return self.invalid(err, position, open.start, end);
+                }
+                let url = href.map(|(url, _)| url).unwrap_or_default();
+                // The `tg://user?id=` URL is a Text Mention.
+                Markdown::InlineURL { title: text, url }.to_text_mention()
+            },
+            _ => {
+                // tg-emoji
+                let id = match open.attribute("emoji-id") {
+                    None => Err((MarkdownErrorType::MissingAttribute("emoji-id".to_string()), open.start)),
+                    Some(id) => id.value.parse::<u64>()
+                        .ok()
+                        .filter(|&id| id > 0)
+                        .ok_or((MarkdownErrorType::InvalidAttribute("emoji-id".to_string()), id.position))
+                };
+                match id {
+                    _ if text.is_empty() => return self.invalid(MarkdownErrorType::EmptyEntity, open.start, open.start, end),
+                    Ok(id) => Markdown::CustomEmoji { emoji: text, id },
+                    Err((err, position)) => return self.invalid(err, position, open.start, end)
+                }
+            }
+        };
+        if value.text().is_empty() {
+            return self.invalid(MarkdownErrorType::EmptyEntity, open.start, open.start, end);
+        }
+        self.add(entity(value, open.start, children));
+    }
+
+    /// Add a node to the current tag, the texts are merged.
+    fn add(&mut self, node: Node) {
+        let Some(frame) = self.stack.last_mut() else {
+            return;
+        };
+        if let Markdown::Text(text) = node.value()
+            && node.inner().is_empty()
+            && let Some(last) = frame.children.last_mut()
+            && last.inner().is_empty()
+            && let Markdown::Text(value) = last.value() {
+            *last = Node::with_position(Markdown::Text(format!("{}{}", value, text)), last.position());
+            return;
+        }
+        frame.children.push(node);
+    }
+
+    /// Handle the unclosed tag of the frame `i`, the closing tag was expected at the end
+    /// of the document.
+    fn unclosed(&mut self, i: usize) {
+        let Some(tag) = self.stack[i].tag.as_ref() else {
+            return;
+        };
+        let expected = Position::default().advance_str(self.document);
+        let error = MarkdownError::with_position(MarkdownErrorType::UnclosedTag(tag.name.clone()), tag.start)
+            .with_expected(expected);
+        return self.fail(i, error);
+    }
+
+    /// Collect the error, drop the frames from `i`, add an error node for the opening tag
+    /// of the frame `i` and continue parsing after it.
+    fn fail(&mut self, i: usize, error: MarkdownError) {
+        self.report(error);
+        let Some(tag) = self.stack[i].tag.as_ref() else {
+            return;
+        };
+        let (start, resync) = (tag.start, tag.end);
+        self.stack.truncate(i);
+        self.add(Node::with_position(
+            Markdown::Error(self.document[start.byte()..resync.byte()].to_string()),
+            start
+        ));
+        self.reader = TagReader::from_position(self.document, resync);
+    }
+
+    /// Handle an invalid tag or element in the region `start..end`, It collects the error
+    /// and adds an error node for the region.
+    fn invalid(
+        &mut self,
+        err: MarkdownErrorType,
+        position: Position,
+        start: Position,
+        end: Position
+    ) {
+        self.report(MarkdownError::with_position(err, position));
+        self.add(Node::with_position(
+            Markdown::Error(self.document[start.byte()..end.byte()].to_string()),
+            start
+        ));
+    }
+
+    /// Collect the error, the errors in a resynced region are reported once.
+    fn report(&mut self, error: MarkdownError) {
+        if !self.errors.iter().any(|e| e.position() == error.position()) {
+            self.errors.push(error);
+        }
+    }
+}
+
+/// Get the entity at the start of the text, It returns the decoded char and the
+/// entity length, the char is `None` if the entity is unknown. It returns `None`
+/// if the text does not start with an entity.
+pub(crate) fn reference(text: &str) -> Option<(Option<char>, usize)> {
+    let body = text.strip_prefix('&')?;
+    let end = body.find(';')?;
+    let name = &body[..end];
+    let len = end + 2;
+    if let Some(number) = name.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) if !hex.is_empty() && hex.bytes().all(|b| b.is_ascii_hexdigit()) => u32::from_str_radix(hex, 16).ok(),
+            None if !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit()) => number.parse::<u32>().ok(),
+            _ => return None
+        };
+        return Some((code.filter(|&c| c != 0).and_then(char::from_u32), len));
+    }
+    if name.is_empty() || !name.bytes().all(|b| b.is_ascii_alphanumeric()) {
+        return None;
+    }
+    let decoded = ENTITIES.iter().find(|(n, _)| *n == name).map(|(_, c)| *c);
+    return Some((decoded, len));
+}
+
+/// Get the document position of the byte `offset` in the decoded `raw` text that
+/// starts at `start`.
+fn source_position(raw: &str, start: Position, offset: usize) -> Position {
+    let (mut position, mut decoded_offset, mut rest) = (start, 0, raw);
+    while let Some(c) = rest.chars().next() {
+        let (decoded, len) = match reference(rest) {
+            Some((Some(decoded), len)) => (decoded.len_utf8(), len),
+            Some((None, len)) => (len, len),
+            None => (c.len_utf8(), c.len_utf8())
+        };
+        if decoded_offset + decoded > offset {
+            return position;
+        }
+        decoded_offset += decoded;
+        position = position.advance_str(&rest[..len]);
+        rest = &rest[len..];
+    }
+    return position;
+}
+
+/// The HTML parser tests.
+#[cfg(test)]
+mod html_parser_tests {
+    use super::{parse_all, parser};
+    use crate::parser::enums::{Markdown, MarkdownErrorType};
+    use crate::parser::types::Node;
+    use crate::parser::url::URLErrorType;
+    use crate::parser::mdv2;
+
+    fn values(node: &Node) -> Vec<&Markdown> {
+        return node.inner().iter().map(|n| n.value()).collect();
+    }
+
+    fn errors(document: &str) -> Vec<(MarkdownErrorType, usize)> {
+        return parse_all(document).1.into_iter().map(|e| (e.err().clone(), e.offset())).collect();
+    }
+
+    #[test]
+    fn same_ast_as_mdv2() {
+        for (html, md) in [
+            ("<b>b</b> <strong>s</strong>", "*b* *s*"),
+            ("<i>i</i> <em>e</em> <u>u</u> <ins>n</ins>", "_i_ _e_ __u__ __n__"),
+            ("<s>s</s> <strike>t</strike> <del>d</del>", "~s~ ~t~ ~d~"),
+            ("<tg-spoiler>a</tg-spoiler> <span class=\"tg-spoiler\">b</span>", "||a|| ||b||"),
+            ("<a href=\"https://a.com\">a <b>b</b></a>", "[a *b*](https://a.com)"),
+            ("<a href='tg://user?id=7'>name</a>", "[name](tg://user?id=7)"),
+            ("<code>a*b</code> <pre>c</pre>", "`a*b` ```c```"),
+            ("<pre><code class=\"language-rust\">let x;</code></pre>", "```rust\nlet x;```"),
+            ("<blockquote>a\nb</blockquote>", ">a\n>b"),
+            ("<blockquote expandable>a</blockquote>", "**>a||"),
+            ("<tg-emoji emoji-id=\"5368324170671202286\">👍</tg-emoji>", "![👍](tg://emoji?id=5368324170671202286)"),
+            ("<B>a <I>b</I></B>", "*a _b_*"),
+        ] {
+            assert_eq!(parser(html).unwrap(), mdv2::parser(md).unwrap(), "in {:?}", html);
+        }
+    }
+
+    #[test]
+    fn tag_errors() {
+        assert_eq!(errors("<b>a</i> </u> <p>b</p> <b"), vec![
+            (MarkdownErrorType::UnclosedTag("b".to_string()), 0),
+            (MarkdownErrorType::MismatchedTag { expected: Some("b".to_string()), found: "i".to_string() }, 4),
+            (MarkdownErrorType::MismatchedTag { expected: Some("b".to_string()), found: "u".to_string() }, 9),
+            (MarkdownErrorType::UnsupportedTag("p".to_string()), 14),
+            (MarkdownErrorType::UnsupportedTag("p".to_string()), 18),
+            (MarkdownErrorType::InvalidTag, 23),
+        ]);
+    }
+
+    #[test]
+    fn nesting() {
+        assert_eq!(errors("<code><b>a</b></code>"), vec![
+            (MarkdownErrorType::TagInCode("b".to_string()), 6),
+            (MarkdownErrorType::MismatchedTag { expected: Some("code".to_string()), found: "b".to_string() }, 10),
+        ]);
+        assert_eq!(errors("<a href=\"https://a.com\"><a href=\"https://b.com\">b</a></a>")[0],
+            (MarkdownErrorType::NestedInlineURL, 24));
+        assert_eq!(errors("<b><blockquote>a</blockquote></b>")[0], (MarkdownErrorType::BlockquoteInEntity, 3));
+    }
+
+    #[test]
+    fn attributes() {
+        assert_eq!(errors("<a>a</a> <a href=\"\">b</a> <a href=\"ftp://c\">c</a>"), vec![
+            (MarkdownErrorType::MissingAttribute("href".to_string()), 0),
+            (MarkdownErrorType::EmptyURL, 18),
+            (MarkdownErrorType::InvalidURL(URLErrorType::UnsupportedScheme), 35),
+        ]);
+        assert_eq!(errors("<span>a</span> <tg-emoji emoji-id=x>b</tg-emoji>"), vec![
+            (MarkdownErrorType::MissingAttribute("class".to_string()), 0),
+            (MarkdownErrorType::MismatchedTag { expected: None, found: "span".to_string() }, 7),
+            (MarkdownErrorType::InvalidAttribute("emoji-id".to_string()), 34),
+        ]);
+    }
+
+    #[test]
+    fn recovery() {
+        let (ast, errors) = parse_all("a <b>b <i>c <u>d</b> <p>");
+        assert_eq!(errors.len(), 2);
+        assert_eq!(values(&ast), vec![
+            &Markdown::Text("a ".to_string()),
+            &Markdown::Bold("b <i>c <u>d".to_string()),
+            &Markdown::Text(" ".to_string()),
+            &Markdown::Error("<p>".to_string()),
+        ]);
+        assert_eq!(values(&ast.inner()[1]), vec![
+            &Markdown::Text("b ".to_string()),
+            &Markdown::Error("<i>".to_string()),
+            &Markdown::Text("c ".to_string()),
+            &Markdown::Error("<u>".to_string()),
+            &Markdown::Text("d".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn entities() {
+        let ast = parser("&lt;b&gt; &amp; &quot;&#65;&#x1F600;&#X41;").unwrap();
+        assert_eq!(values(&ast), vec![&Markdown::Text("<b> & \"A😀A".to_string())]);
+        let ast = parser("<code>a &lt; b</code> <a href=\"https://a.com/?a=1&amp;b=2\">c</a>").unwrap();
+        assert_eq!(values(&ast), vec![
+            &Markdown::FixedWidthCode("a < b".to_string()),
+            &Markdown::Text(" ".to_string()),
+            &Markdown::InlineURL { title: "c".to_string(), url: "https://a.com/?a=1&b=2".to_string() },
+        ]);
+    }
+
+    #[test]
+    fn entity_errors() {
+        assert_eq!(errors("😀 &nbsp; a & b 1<2 &#0; &amp"), vec![
+            (MarkdownErrorType::UnknownEntity("&nbsp;".to_string()), 2),
+            (MarkdownErrorType::UnescapedCharacter('&'), 11),
+            (MarkdownErrorType::UnescapedCharacter('<'), 16),
+            (MarkdownErrorType::UnknownEntity("&#0;".to_string()), 19),
+            (MarkdownErrorType::UnescapedCharacter('&'), 24),
+        ]);
+        let (ast, _) = parse_all("a & b");
+        assert_eq!(values(&ast), vec![&Markdown::Text("a & b".to_string())]);
+        let err = parser("<a href=\"https://a.com/&lt;&amp;x y\">a</a>").unwrap_err();
+        assert_eq!(err.err(), &MarkdownErrorType::InvalidURL(URLErrorType::Whitespace));
+        assert_eq!(err.offset(), 33);
+    }
+}
@@ -1,15 +1,16 @@
 use crate::lexer::mdv1;
-use crate::lexer::types::MDToken;
+use crate::lexer::types::{MDToken, Position};
 use crate::lexer::tokens::MDTokensType;
-use crate::reader::VecReader;
+use crate::reader::IterReader;
 
 use super::types::{MarkdownError, Node};
+use super::url;
 use super::enums::{Markdown, MarkdownErrorType};
 
 /// The Markdown V1 parser. this function use the markdown v1 lexer, it will
-/// lex the document and then parse it to an AST.
+/// lex the document and then parse it to an AST, It stops on the first error.
 /// 
-/// ### Example: 
+/// ### Example:
 /// ```
 /// use telemark::parser::mdv1;
 /// use telemark::parser::enums::Markdown;
@@ -17,8 +18,8 @@ use super::enums::{Markdown, MarkdownErrorType};
 /// if let Ok(ast) = mdv1::parser("*bold*") {
 ///     assert_eq!(ast.value(), &Markdown::Document);
 ///     assert_eq!(ast.inner().len(), 1);
-///     assert_eq!( 
-///         ast.inner()[0].value(), 
+///     assert_eq!(
+///         ast.inner()[0].value(),
 ///         &Markdown::Bold("bold".to_string())
 ///     );
 /// }
@@ -31,187 +32,490 @@ use super::enums::{Markdown, MarkdownErrorType};
 /// }
 /// ```
 pub fn parser(document: &str) -> Result<Node, MarkdownError> {
-    let mut tokens: VecReader<MDToken<'_>> = VecReader::new(mdv1::lex(document));
-    let mut node = Node::new(Markdown::Document);
-    let mut escaped_count: usize = 0;
-    let mut _start: usize = 0; // for the errors position
-    while let Some(t) = tokens.next() {
-        _start = t.offset();
-        match t.token_type() {
-            MDTokensType::Escape => {
-                escaped_count += 1;
-                let escaped = [
-                    MDTokensType::Star, MDTokensType::Underscore, MDTokensType::Backtick, 
-                    MDTokensType::Backticks, MDTokensType::SquareBracketsOpen
-                ];
-                if let Some(nt) = tokens.next() {
-                    if let Some(_) = escaped.iter().find(|&st| st == nt.token_type()) {
-                        if escaped_count%2 != 0 {
-                            node.add_inner(
-                                Node::new(
-                                    Markdown::Text(
-                                        nt.value().to_string()
-                                    )
-                                )
-                            );
-                            escaped_count = 0;
-                        } else {
-                            tokens.go_back();
+    let mut parser = Parser::new(document, false);
+    let node = parser.parse();
+    return match parser.errors.into_iter().next() {
+        Some(err) => Err(err),
+        None => Ok(node)
+    };
+}
+
+/// The Markdown V1 parser with error recovery, It collects all the errors
+/// in the document instead of stopping at the first one. After an unclosed
+/// entity it continues parsing right after the opening delimiter, and the
+/// broken region is kept in the AST as a `Markdown::Error` node.
+/// 
+/// ### Example:
+/// ```
+/// use telemark::parser::mdv1;
+/// use telemark::parser::enums::{Markdown, MarkdownErrorType};
+/// 
+/// let (ast, errors) = mdv1::parse_all("*not closed _italic_ [link");
+/// assert_eq!(errors.len(), 2);
+/// assert_eq!(errors[0].err(), &MarkdownErrorType::StarOpen);
+/// assert_eq!(errors[0].offset(), 0);
+/// assert_eq!(errors[1].err(), &MarkdownErrorType::SquareBracketsOpen);
+/// assert_eq!(errors[1].offset(), 21);
+/// 
+/// assert_eq!(ast.inner()[0].value(), &Markdown::Error("*".to_string()));
+/// assert_eq!(ast.inner()[2].value(), &Markdown::Italic("italic".to_string()));
+/// ```
+pub fn parse_all(document: &str) -> (Node, Vec<MarkdownError>) {
+    let mut parser = Parser::new(document, true);
+    let node = parser.parse();
+    return (node, parser.errors);
+}
+
+/// The Markdown V1 escaped tokens, the first four are the formatting tokens.
+const ESCAPED: [MDTokensType; 5] = [
+    MDTokensType::Star, MDTokensType::Underscore, MDTokensType::Backtick,
+    MDTokensType::Backticks, MDTokensType::SquareBracketsOpen
+];
+
+/// The text between the entity delimiters.
+struct Until {
+    /// The text without the escapes.
+    value: String,
+    /// The position after the closing delimiter.
+    end: Position,
+    /// The position of the first not escaped formatting token in the text.
+    formatting: Option<Position>
+}
+
+/// The Markdown V1 parser state.
+struct Parser<'a> {
+    document: &'a str,
+    tokens: IterReader<mdv1::Lexer<'a>>,
+    recover: bool,
+    errors: Vec<MarkdownError>
+}
+impl<'a> Parser<'a> {
+    /// Create new `Parser`, if `recover` is false it will stop on the first error.
+    fn new(document: &'a str, recover: bool) -> Self {
+        return Self {
+            document,
+            tokens: IterReader::new(mdv1::lex(document)),
+            recover,
+            errors: Vec::new()
+        };
+    }
+
+    /// Parse the document to an AST.
+    fn parse(&mut self) -> Node {
+        let mut node = Node::new(Markdown::Document);
+        while let Some(t) = self.tokens.next() {
+            // It stops on the first error if It does not recover.
+            if !self.recover && !self.errors.is_empty() {
+                break;
+            }
+            let t = t.clone();
+            let start = t.position();
+            match t.token_type() {
+                // The backslash escapes only the formatting tokens, otherwise It is a text.
+                MDTokensType::Escape => match self.tokens.next().cloned() {
+                    Some(nt) if ESCAPED.contains(nt.token_type()) => node.add_inner(
+                        Node::with_position(
+                            Markdown::Text(
+                                nt.value().to_string()
+                            ),
+                            nt.position()
+                        )
+                    ),
+                    next => {
+                        if next.is_some() {
+                            self.tokens.go_back();
                         }
+                        node.add_inner(Node::with_position(Markdown::Text(t.value().to_string()), start));
                     }
-                }
-                continue;
-            },
-            MDTokensType::Star => node.add_inner(
-                Node::new(
-                    Markdown::Bold(
-                        parse_until(
-                            &mut tokens, 
-                            MDTokensType::Star, 
-                            _start, 
-                            MarkdownErrorType::StarOpen
-                        )?
-                    )
-                )
-            ),
-            MDTokensType::Underscore => node.add_inner(
-                Node::new(
-                    Markdown::Italic(
-                        parse_until(
-                            &mut tokens, 
-                            MDTokensType::Underscore,
-                            _start,
-                            MarkdownErrorType::UnderscoreOpen
-                        )?
-                    )
-                )
-            ),
-            MDTokensType::Backtick => node.add_inner(
-                Node::new(
-                    Markdown::FixedWidthCode(
-                        parse_until(
-                            &mut tokens, 
-                            MDTokensType::Backtick,
-                            _start,
-                            MarkdownErrorType::BacktickOpen
-                        )?
-                    )
-                )
-            ),
-            MDTokensType::Backticks => {
-                let value: String = parse_until(
-                    &mut tokens, 
-                    MDTokensType::Backticks,
-                    _start,
-                    MarkdownErrorType::BackticksOpen
-                )?;
-                let lines: Vec<&str> = value.split('\n').collect();
-                // ```\n code \n```
-                if lines.len() > 1 {
-                    node.add_inner(Node::new(
-                        Markdown::PreFormattedFixedWidthCode { 
-                            lang: if lines[0].len() > 0 { Some(lines[0].to_string()) } else { None }, 
-                            code: lines[1..].join("\n").to_string()
+                },
+                MDTokensType::Star => self.entity(
+                    &mut node,
+                    &t,
+                    MDTokensType::Star,
+                    MarkdownErrorType::StarOpen,
+                    Markdown::Bold
+                ),
+                MDTokensType::Underscore => self.entity(
+                    &mut node,
+                    &t,
+                    MDTokensType::Underscore,
+                    MarkdownErrorType::UnderscoreOpen,
+                    Markdown::Italic
+                ),
+                MDTokensType::Backtick => self.entity(
+                    &mut node,
+                    &t,
+                    MDTokensType::Backtick,
+                    MarkdownErrorType::BacktickOpen,
+                    Markdown::FixedWidthCode
+                ),
+                MDTokensType::Backticks => match self.parse_until(MDTokensType::Backticks) {
+                    Some(until) => {
+                        let value = until.value;
+                        let lines: Vec<&str> = value.split('\n').collect();
+                        // ```\n code \n```
+                        let pre = if lines.len() > 1 {
+                            Markdown::PreFormattedFixedWidthCode {
+                                lang: if !lines[0].is_empty() { Some(lines[0].to_string()) } else { None },
+                                code: lines[1..].join("\n").to_string()
+                            }
                         }
-                    ));
-                } 
-                // ``` code ```
-                else {
-                    node.add_inner(Node::new(
-                        Markdown::PreFormattedFixedWidthCode { 
-                            lang: None, 
-                            code: value
+                        // ``` code ```
+                        else {
+                            Markdown::PreFormattedFixedWidthCode {
+                                lang: None,
+                                code: value
+                            }
+                        };
+                        if matches!(&pre, Markdown::PreFormattedFixedWidthCode { code, .. } if code.is_empty()) {
+                            self.invalid(&mut node, MarkdownErrorType::EmptyEntity, start, start, until.end);
+                        } else {
+                            node.add_inner(Node::with_position(pre, start));
                         }
-                    ));
-                }
-            },
-            MDTokensType::SquareBracketsOpen => {
-                let title = parse_until(
-                    &mut tokens, 
-                    MDTokensType::SquareBracketsClose,
-                    _start,
-                    MarkdownErrorType::SquareBracketsOpen
-                )?;
-                if let Some(nt) = tokens.next() {
-                    if *nt.token_type() == MDTokensType::ParenthesesOpen {
-                        node.add_inner(Node::new(
-                            Markdown::InlineURL { 
-                                title, 
-                                url: parse_until(
-                                    &mut tokens, 
-                                    MDTokensType::ParenthesesClose,
-                                    _start,
-                                    MarkdownErrorType::ParenthesesOpen
-                                )?
+                    },
+                    None => self.unclosed(&mut node, MarkdownErrorType::BackticksOpen, start, after(&t))
+                },
+                MDTokensType::SquareBracketsOpen => {
+                    let Some(title) = self.parse_until(MDTokensType::SquareBracketsClose) else {
+                        self.unclosed(&mut node, MarkdownErrorType::SquareBracketsOpen, start, after(&t));
+                        continue;
+                    };
+                    match self.tokens.next().cloned() {
+                        Some(nt) if *nt.token_type() == MDTokensType::ParenthesesOpen => {
+                            match self.parse_until(MDTokensType::ParenthesesClose) {
+                                Some(url) => {
+                                    let err = if title.value.is_empty() {
+                                        Some((MarkdownErrorType::EmptyEntity, start))
+                                    } else if let Some(formatting) = title.formatting {
+                                        Some((MarkdownErrorType::FormattingInURLTitle, formatting))
+                                    } else if url.value.trim().is_empty() {
+                                        Some((MarkdownErrorType::EmptyURL, after(&nt)))
+                                    } else if let Err(err) = url::validate(&url.value) {
+                                        Some((
+                                            MarkdownErrorType::InvalidURL(err.err().clone()),
+                                            self.source_position(after(&nt), MDTokensType::ParenthesesClose, err.offset())
+                                        ))
+                                    } else {
+                                        None
+                                    };
+                                    match err {
+                                        Some((err, position)) => self.invalid(&mut node, err, position, start, url.end),
+                                        // The `tg://user?id=` URL is a Text Mention.
+                                        None => node.add_inner(Node::with_position(
+                                            Markdown::InlineURL {
+                                                title: title.value,
+                                                url: url.value
+                                            }.to_text_mention(),
+                                            start
+                                        ))
+                                    }
+                                },
+                                None => self.unclosed(
+                                    &mut node,
+                                    MarkdownErrorType::InlineURLOpen,
+                                    start,
+                                    after(&nt)
+                                )
                             }
-                        ));
+                        },
+                        // It is not Inline URL
+                        Some(_) => {
+                            self.tokens.go_back();
+                            node.add_inner(Node::with_position(Markdown::Text(title.value), start));
+                        },
+                        // It is not Inline URL
+                        None => node.add_inner(Node::with_position(Markdown::Text(title.value), start))
                     }
-                    // It is not Inline URL
-                    else {
-                        tokens.go_back();
-                        node.add_inner(Node::new(Markdown::Text(title)));
-                    }
-                }
-                // It is not Inline URL
-                else {
-                    node.add_inner(Node::new(Markdown::Text(title)));
-                }
-            },
-            MDTokensType::ParenthesesOpen => node.add_inner(
-                Node::new(
-                    Markdown::Text(
-                        format!(
-                            "({})", 
-                            parse_until(
-                                &mut tokens, 
-                                MDTokensType::ParenthesesClose,
-                                _start,
-                                MarkdownErrorType::ParenthesesOpen
-                            )?
+                },
+                MDTokensType::ParenthesesOpen => match self.parse_until(MDTokensType::ParenthesesClose) {
+                    Some(until) => node.add_inner(
+                        Node::with_position(
+                            Markdown::Text(
+                                format!("({})", until.value)
+                            ),
+                            start
                         )
+                    ),
+                    None => self.unclosed(&mut node, MarkdownErrorType::ParenthesesOpen, start, after(&t))
+                },
+                _ => node.add_inner(
+                    Node::with_position(
+                        Markdown::Text(
+                            t.value().to_string()
+                        ),
+                        start
                     )
                 )
+            }
+        }
+        return node;
+    }
+
+    /// Parse a simple entity like `*bold*` and add it to the node.
+    fn entity(
+        &mut self,
+        node: &mut Node,
+        open: &MDToken<'a>,
+        to: MDTokensType,
+        err: MarkdownErrorType,
+        value: fn(String) -> Markdown
+    ) {
+        match self.parse_until(to) {
+            Some(until) if until.value.is_empty() => self.invalid(
+                node,
+                MarkdownErrorType::EmptyEntity,
+                open.position(),
+                open.position(),
+                until.end
             ),
-            _ => node.add_inner(
-                Node::new(
-                    Markdown::Text(
-                        t.value().to_string()
-                    )
-                )
-            )
+            Some(until) => node.add_inner(Node::with_position(value(until.value), open.position())),
+            None => self.unclosed(node, err, open.position(), after(open))
+        }
+    }
+
+    /// Get the text until the `to` token, It returns `None` if the `to` token not found.
+    /// The backslash escapes the formatting tokens and the `to` token, otherwise It is a text.
+    fn parse_until(&mut self, to: MDTokensType) -> Option<Until> {
+        let mut value = String::new();
+        let mut formatting: Option<Position> = None;
+        while let Some(t) = self.tokens.next() {
+            let t = t.clone();
+            if *t.token_type() == MDTokensType::Escape {
+                // The closing token can be escaped too.
+                match self.tokens.next() {
+                    Some(nt) if ESCAPED.contains(nt.token_type()) || *nt.token_type() == to => {
+                        value += nt.value();
+                        continue;
+                    },
+                    Some(_) => self.tokens.go_back(),
+                    None => {}
+                }
+            } else if *t.token_type() == to {
+                return Some(Until {
+                    value,
+                    end: after(&t),
+                    formatting
+                });
+            } else if ESCAPED[..4].contains(t.token_type()) {
+                formatting.get_or_insert(t.position());
+            }
+            value += t.value();
         }
-        escaped_count = 0;
+        return None;
+    }
+
+    /// Get the document position of the byte `offset` in the text that `parse_until` 
+    /// returned for the entity content that starts from `start` and ends with `to`.
+    fn source_position(&self, start: Position, to: MDTokensType, offset: usize) -> Position {
+        let mut value_offset: usize = 0;
+        let mut tokens = mdv1::Lexer::from_position(self.document, start).peekable();
+        while let Some(t) = tokens.next() {
+            // The `parse_until` drops the escapes of the formatting tokens and the `to` token.
+            if *t.token_type() == MDTokensType::Escape
+                && tokens.peek().is_some_and(|nt| ESCAPED.contains(nt.token_type()) || *nt.token_type() == to) {
+                continue;
+            }
+            if value_offset + t.value().len() > offset {
+                return t.position().advance_str(&t.value()[..offset-value_offset]);
+            }
+            value_offset += t.value().len();
+        }
+        return start;
+    }
+
+    /// Handle a parsed but invalid entity in the region `start..end`, It collects the error
+    /// and adds an error node for the region.
+    fn invalid(
+        &mut self,
+        node: &mut Node,
+        err: MarkdownErrorType,
+        position: Position,
+        start: Position,
+        end: Position
+    ) {
+        self.errors.push(MarkdownError::with_position(err, position));
+        node.add_inner(Node::with_position(
+            Markdown::Error(self.document[start.byte()..end.byte()].to_string()),
+            start
+        ));
+    }
+
+    /// Handle an unclosed entity that starts at `start`, It collects the error, adds an
+    /// error node for the region `start..resync` and continues parsing from `resync`.
+    fn unclosed(
+        &mut self,
+        node: &mut Node,
+        err: MarkdownErrorType,
+        start: Position,
+        resync: Position
+    ) {
+        // The closing delimiter was expected before the end of the document, the tokens
+        // were read until the end looking for it, so the lexer is at the end.
+        let end = self.tokens.items().next_position();
+        self.errors.push(MarkdownError::with_position(err, start).with_expected(end));
+        node.add_inner(Node::with_position(
+            Markdown::Error(self.document[start.byte()..resync.byte()].to_string()),
+            start
+        ));
+        self.tokens = IterReader::new(mdv1::Lexer::from_position(self.document, resync));
     }
-    return Ok(node);
 }
 
-fn parse_until(
-    tokens: &mut VecReader<MDToken<'_>>, 
-    to: MDTokensType, 
-    start: usize, 
-    err: MarkdownErrorType
-) -> Result<String, MarkdownError> {
-    let mut value = String::new();
-    let mut found = false;
-    let mut escaped_counter: usize = 0;
-    while let Some(t) = tokens.next() {
-        if *t.token_type() == MDTokensType::Escape {
-            escaped_counter += 1;
-            continue;
-        } else if *t.token_type() == to && escaped_counter%2 == 0 {
-            found = true; 
-            break;
+/// Get the position after the token.
+fn after(t: &MDToken<'_>) -> Position {
+    return t.position().advance_str(t.value());
+}
+
+/// The Markdown V1 parser tests.
+#[cfg(test)]
+mod mdv1_parser_tests {
+    use super::{parse_all, parser};
+    use crate::parser::enums::{Markdown, MarkdownErrorType};
+    use crate::parser::url::URLErrorType;
+    use crate::render::plain;
+
+    #[test]
+    fn first_error() {
+        let err = parser("ok *a _b").unwrap_err();
+        assert_eq!(err.err(), &MarkdownErrorType::StarOpen);
+        assert_eq!(err.offset(), 3);
+    }
+
+    #[test]
+    fn all_errors() {
+        let (_, errors) = parse_all("*a\n_b\n`c\n```d\n(f\n[g](h\n[e");
+        let errs: Vec<(&MarkdownErrorType, usize)> = errors.iter().map(|e| (e.err(), e.offset())).collect();
+        assert_eq!(errs, vec![
+            (&MarkdownErrorType::StarOpen, 0),
+            (&MarkdownErrorType::UnderscoreOpen, 3),
+            (&MarkdownErrorType::BacktickOpen, 6),
+            (&MarkdownErrorType::BackticksOpen, 9),
+            (&MarkdownErrorType::ParenthesesOpen, 14),
+            (&MarkdownErrorType::InlineURLOpen, 17),
+            (&MarkdownErrorType::SquareBracketsOpen, 23),
+        ]);
+    }
+
+    #[test]
+    fn resync() {
+        let (ast, errors) = parse_all("[t](u *b* _i");
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].err(), &MarkdownErrorType::InlineURLOpen);
+        let values: Vec<&Markdown> = ast.inner().iter().map(|n| n.value()).collect();
+        assert_eq!(values, vec![
+            &Markdown::Error("[t](".to_string()),
+            &Markdown::Text("u ".to_string()),
+            &Markdown::Bold("b".to_string()),
+            &Markdown::Text(" ".to_string()),
+            &Markdown::Error("_".to_string()),
+            &Markdown::Text("i".to_string()),
+        ]);
+        assert_eq!(ast.inner()[4].position().char(), 10);
+    }
+
+    #[test]
+    fn no_errors() {
+        let document = "*a* _b_ `c` [d](e)";
+        let (ast, errors) = parse_all(document);
+        assert!(errors.is_empty());
+        assert_eq!(Ok(ast), parser(document));
+    }
+
+    #[test]
+    fn escaped() {
+        let ast = parser("\\*a\\* \\b").unwrap();
+        let values: Vec<&Markdown> = ast.inner().iter().map(|n| n.value()).collect();
+        assert_eq!(values, vec![
+            &Markdown::Text("*".to_string()),
+            &Markdown::Text("a".to_string()),
+            &Markdown::Text("*".to_string()),
+            &Markdown::Text(" ".to_string()),
+            &Markdown::Text("\\".to_string()),
+            &Markdown::Text("b".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn backslash_is_text() {
+        // Only `_`, `*`, `` ` `` and `[` are escaped, the other backslashes are kept.
+        let ast = parser("C:\\dir\\ \\\\*a\\* \\(x) _a\\b_ `\\d+`\\").unwrap();
+        assert_eq!(plain::render(&ast), "C:\\dir\\ \\*a* \\(x) a\\b \\d+\\");
+        let entities: Vec<&Markdown> = ast.inner().iter()
+            .map(|n| n.value())
+            .filter(|v| !matches!(v, Markdown::Text(_)))
+            .collect();
+        assert_eq!(entities, vec![
+            &Markdown::Italic("a\\b".to_string()),
+            &Markdown::FixedWidthCode("\\d+".to_string()),
+        ]);
+        let err = parser("[a](https://a.com/\\ x)").unwrap_err();
+        assert_eq!(err.err(), &MarkdownErrorType::InvalidURL(URLErrorType::Whitespace));
+        assert_eq!(err.offset(), 19);
+    }
+
+    #[test]
+    fn empty_entities() {
+        for document in ["**", "__", "``", "``````", "```rust\n```", "[](https://example.com)"] {
+            let err = parser(document).unwrap_err();
+            assert_eq!(err.err(), &MarkdownErrorType::EmptyEntity, "in {:?}", document);
+            assert_eq!(err.offset(), 0);
+        }
+    }
+
+    #[test]
+    fn empty_url() {
+        for document in ["[a]()", "[a](  )", "[a](\n)"] {
+            let err = parser(document).unwrap_err();
+            assert_eq!(err.err(), &MarkdownErrorType::EmptyURL);
+            assert_eq!(err.offset(), 4);
         }
-        value += t.value();
-        escaped_counter = 0;
-    }
-    if !found {
-        return Err(
-            MarkdownError::new(
-                err, 
-                start
-            )
-        )
-    }
-    return Ok(value);
-}
\ No newline at end of file
+    }
+
+    #[test]
+    fn formatting_in_url_title() {
+        let err = parser("see [a *b*](https://example.com)").unwrap_err();
+        assert_eq!(err.err(), &MarkdownErrorType::FormattingInURLTitle);
+        assert_eq!(err.offset(), 7);
+        assert!(parser("[a \\*b](https://example.com)").is_ok());
+    }
+
+    #[test]
+    fn invalid_regions() {
+        let (ast, errors) = parse_all("** [](u) *b*");
+        assert_eq!(errors.len(), 2);
+        let values: Vec<&Markdown> = ast.inner().iter().map(|n| n.value()).collect();
+        assert_eq!(values, vec![
+            &Markdown::Error("**".to_string()),
+            &Markdown::Text(" ".to_string()),
+            &Markdown::Error("[](u)".to_string()),
+            &Markdown::Text(" ".to_string()),
+            &Markdown::Bold("b".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn invalid_url() {
+        let err = parser("[a](https://exa mple.com)").unwrap_err();
+        assert_eq!(err.err(), &MarkdownErrorType::InvalidURL(URLErrorType::Whitespace));
+        assert_eq!(err.offset(), 15);
+        // The escapes are not counted in the URL but they are counted in the document.
+        let err = parser("😀 [a](https://a\\_b.com/%z)").unwrap_err();
+        assert_eq!(err.err(), &MarkdownErrorType::InvalidURL(URLErrorType::InvalidPercentEncoding));
+        assert_eq!(err.offset(), 23);
+        assert_eq!(err.position().utf16(), 24);
+    }
+
+    #[test]
+    fn text_mention() {
+        let ast = parser("hi [Mohaned](tg://user?id=123456)!").unwrap();
+        assert_eq!(
+            ast.inner()[1].value(),
+            &Markdown::TextMention { name: "Mohaned".to_string(), user_id: 123456 }
+        );
+        let ast = parser("[a](tg://resolve?domain=x)").unwrap();
+        assert_eq!(
+            ast.inner()[0].value(),
+            &Markdown::InlineURL { title: "a".to_string(), url: "tg://resolve?domain=x".to_string() }
+        );
+    }
+}
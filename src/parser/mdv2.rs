@@ -0,0 +1,884 @@
+use crate::lexer::mdv2;
+use crate::lexer::types::{MDToken, Position};
+use crate::lexer::tokens::MDTokensType;
+use crate::reader::IterReader;
+
+use super::types::{entity, visible_text, MarkdownError, Node};
+use super::enums::{Markdown, MarkdownErrorType};
+use super::url;
+
+/// The Markdown V2 parser. this function use the markdown v2 lexer, it will
+/// lex the document and then parse it to a nested AST, It stops on the first error.
+/// 
+/// The formatting nodes hold their visible text, and if their content is not
+/// a plain text the inner list holds the nested nodes.
+/// 
+/// ### Example:
+/// ```
+/// use telemark::parser::mdv2;
+/// use telemark::parser::enums::{Markdown, MarkdownErrorType};
+/// 
+/// let ast = mdv2::parser("*bold _italic bold_*").unwrap();
+/// let bold = &ast.inner()[0];
+/// assert_eq!(bold.value(), &Markdown::Bold("bold italic bold".to_string()));
+/// assert_eq!(bold.inner()[0].value(), &Markdown::Text("bold ".to_string()));
+/// assert_eq!(bold.inner()[1].value(), &Markdown::Italic("italic bold".to_string()));
+/// 
+/// let err = mdv2::parser("*bold _italic* bold_").unwrap_err();
+/// assert_eq!(err.err(), &MarkdownErrorType::UnderscoreOpen);
+/// assert_eq!(err.offset(), 6);
+/// 
+/// // The reserved chars must be escaped outside the code and the URLs.
+/// let err = mdv2::parser("the end.").unwrap_err();
+/// assert_eq!(err.err(), &MarkdownErrorType::UnescapedCharacter('.'));
+/// assert_eq!(err.offset(), 7);
+/// assert!(mdv2::parser("the end\\. `a.b`").is_ok());
+/// ```
+pub fn parser(document: &str) -> Result<Node, MarkdownError> {
+    let mut parser = Parser::new(document, false);
+    let node = parser.parse();
+    return match parser.errors.into_iter().next() {
+        Some(err) => Err(err),
+        None => Ok(node)
+    };
+}
+
+/// The Markdown V2 parser with error recovery, It collects all the errors
+/// in the document instead of stopping at the first one. After an unclosed
+/// entity it continues parsing right after the opening delimiter, and the
+/// broken region is kept in the AST as a `Markdown::Error` node.
+/// 
+/// ### Example:
+/// ```
+/// use telemark::parser::mdv2;
+/// use telemark::parser::enums::{Markdown, MarkdownErrorType};
+/// 
+/// let (ast, errors) = mdv2::parse_all("*a _b* `c");
+/// assert_eq!(errors.len(), 2);
+/// assert_eq!(errors[0].err(), &MarkdownErrorType::UnderscoreOpen);
+/// assert_eq!(errors[1].err(), &MarkdownErrorType::BacktickOpen);
+/// assert_eq!(ast.inner()[0].value(), &Markdown::Bold("a _b".to_string()));
+/// ```
+pub fn parse_all(document: &str) -> (Node, Vec<MarkdownError>) {
+    let mut parser = Parser::new(document, true);
+    let node = parser.parse();
+    let mut errors = parser.errors;
+    errors.sort_by_key(|err| err.position().byte());
+    return (node, errors);
+}
+
+/// An open entity, It waits for its closing delimiter.
+struct Frame {
+    /// The opening delimiter type, the document frame is `Text`.
+    kind: MDTokensType,
+    /// The position of the opening delimiter.
+    start: Position,
+    /// The position after the opening delimiter.
+    resync: Position,
+    /// The parsed inner nodes.
+    children: Vec<Node>
+}
+
+/// The raw content of the code or the URL.
+struct Raw {
+    /// The content with the escapes parsed.
+    value: String,
+    /// The position after the closing delimiter.
+    end: Position,
+    /// The positions of the backslashes that do not escape any char.
+    backslashes: Vec<Position>
+}
+
+/// The Markdown V2 parser state.
+struct Parser<'a> {
+    document: &'a str,
+    tokens: IterReader<mdv2::Lexer<'a>>,
+    recover: bool,
+    errors: Vec<MarkdownError>,
+    end: Option<Position>,
+    stack: Vec<Frame>
+}
+impl<'a> Parser<'a> {
+    /// Create new `Parser`, if `recover` is false it will stop on the first error.
+    fn new(document: &'a str, recover: bool) -> Self {
+        return Self {
+            document,
+            tokens: IterReader::new(mdv2::lex(document)),
+            recover,
+            errors: Vec::new(),
+            end: None,
+            stack: Vec::new()
+        };
+    }
+
+    /// Parse the document to an AST.
+    fn parse(&mut self) -> Node {
+        self.stack = vec![Frame {
+            kind: MDTokensType::Text,
+            start: Position::default(),
+            resync: Position::default(),
+            children: Vec::new()
+        }];
+        loop {
+            // It stops on the first error if It does not recover.
+            if !self.recover && !self.errors.is_empty() {
+                break;
+            }
+            let Some(t) = self.tokens.next().cloned() else {
+                if self.stack.len() > 1 {
+                    let end = self.end();
+                    match self.quote() {
+                        Some(q) => self.close_quote(q, end),
+                        // The outer open entity is reported first.
+                        None => self.unclosed(1, end)
+                    }
+                    continue;
+                }
+                break;
+            };
+            self.token(&t);
+        }
+        let mut node = Node::new(Markdown::Document);
+        for child in self.stack.pop().map(|f| f.children).unwrap_or_default() {
+            node.add_inner(child);
+        }
+        return node;
+    }
+
+    /// Parse one token.
+    fn token(&mut self, t: &MDToken<'a>) {
+        match t.token_type() {
+            MDTokensType::Escape => self.text(escaped(t.value()), t.position()),
+            MDTokensType::Star
+            | MDTokensType::Underscore
+            | MDTokensType::DoubleUnderscore
+            | MDTokensType::Tilde => self.delimiter(t.token_type(), t.position(), after(t)),
+            MDTokensType::DoubleStar if self.next_is(MDTokensType::GreaterThan) => self.quote_open(t),
+            MDTokensType::DoubleStar => {
+                // The `**` is a closing and an opening bold.
+                let middle = t.position().advance('*');
+                self.delimiter(&MDTokensType::Star, t.position(), middle);
+                self.delimiter(&MDTokensType::Star, middle, after(t));
+            },
+            MDTokensType::Backtick => self.code(t),
+            MDTokensType::Backticks => self.pre(t),
+            MDTokensType::SquareBracketsOpen => self.url_open(t),
+            MDTokensType::ExclamationMark if self.next_is(MDTokensType::SquareBracketsOpen) => self.url_open(t),
+            MDTokensType::SquareBracketsClose => self.url_close(t),
+            MDTokensType::GreaterThan if self.line_start(t.position()) => self.quote_open(t),
+            MDTokensType::DoublePipe => self.pipes(t),
+            MDTokensType::Text => self.lines(t),
+            _ => self.unescaped(t)
+        }
+    }
+
+    /// Open or close the entity of the `kind` delimiter.
+    fn delimiter(&mut self, kind: &MDTokensType, start: Position, end: Position) {
+        let Some(i) = self.stack.iter().rposition(|f| f.kind == *kind) else {
+            self.stack.push(Frame {
+                kind: kind.clone(),
+                start,
+                resync: end,
+                children: Vec::new()
+            });
+            return;
+        };
+        // The inner entity must be closed before its parent.
+        if i != self.stack.len() - 1 {
+            return self.unclosed(i + 1, start);
+        }
+        let Some(frame) = self.stack.pop() else {
+            return;
+        };
+        let text = visible_text(&frame.children);
+        let value = match kind {
+            MDTokensType::Star => Markdown::Bold(text),
+            MDTokensType::DoubleUnderscore => Markdown::Underline(text),
+            MDTokensType::Tilde => Markdown::Strikethrough(text),
+            MDTokensType::DoublePipe => Markdown::Spoiler(text),
+            _ => Markdown::Italic(text)
+        };
+        return self.close(frame, value, end);
+    }
+
+    /// Add the entity node of the closed frame to the parent, the empty entities are errors.
+    fn close(&mut self, frame: Frame, value: Markdown, end: Position) {
+        if value.text().is_empty() {
+            return self.invalid(MarkdownErrorType::EmptyEntity, frame.start, frame.start, end);
+        }
+        self.add(entity(value, frame.start, frame.children));
+    }
+
+    /// Open the Blockquote or the Expandable Blockquote, the `**` is followed by the `>` token.
+    fn quote_open(&mut self, open: &MDToken<'a>) {
+        let expandable = *open.token_type() == MDTokensType::DoubleStar;
+        let end = if expandable {
+            match self.tokens.next() {
+                Some(t) => after(t),
+                None => after(open)
+            }
+        } else {
+            after(open)
+        };
+        if !self.line_start(open.position()) {
+            return self.invalid(MarkdownErrorType::BlockquoteNotAtLineStart, open.position(), open.position(), end);
+        }
+        if self.stack.len() > 1 {
+            return self.invalid(MarkdownErrorType::BlockquoteInEntity, open.position(), open.position(), end);
+        }
+        self.stack.push(Frame {
+            kind: open.token_type().clone(),
+            start: open.position(),
+            resync: end,
+            children: Vec::new()
+        });
+    }
+
+    /// Close the Expandable Blockquote if the `||` is at the end of its last line and
+    /// no Spoiler is waiting for it, otherwise It is a Spoiler delimiter.
+    fn pipes(&mut self, t: &MDToken<'a>) {
+        let line_end = match self.tokens.next() {
+            Some(next) => next.value().starts_with('\n'),
+            None => true
+        };
+        self.tokens.go_back();
+        let spoiler = self.stack.last().is_some_and(|f| f.kind == MDTokensType::DoublePipe);
+        match self.quote() {
+            Some(q) if line_end && !spoiler && self.stack[q].kind == MDTokensType::DoubleStar => {
+                if q != self.stack.len() - 1 {
+                    return self.unclosed(q + 1, t.position());
+                }
+                let Some(frame) = self.stack.pop() else {
+                    return;
+                };
+                let value = Markdown::ExpandableBlockquote(visible_text(&frame.children));
+                return self.close(frame, value, after(t));
+            },
+            _ => return self.delimiter(&MDTokensType::DoublePipe, t.position(), after(t))
+        }
+    }
+
+    /// Close the Blockquote at the end of its last line, the position is the end of the line.
+    /// The Expandable Blockquote must be closed by `||`.
+    fn close_quote(&mut self, q: usize, position: Position) {
+        if q != self.stack.len() - 1 {
+            return self.unclosed(q + 1, position);
+        }
+        if self.stack[q].kind == MDTokensType::DoubleStar {
+            return self.unclosed(q, position);
+        }
+        let Some(frame) = self.stack.pop() else {
+            return;
+        };
+        let value = Markdown::Blockquote(visible_text(&frame.children));
+        return self.close(frame, value, position);
+    }
+
+    /// Add the text token, If a Blockquote is open its lines are checked, the
+    /// Blockquote continues if the next line starts with `>`.
+    fn lines(&mut self, t: &MDToken<'a>) {
+        // The `\r` in `___italic underline_\r__` separates the italic and
+        // the underline delimiters, It is ignored.
+        if t.value() == "\r" && self.next_is(MDTokensType::DoubleUnderscore) {
+            return;
+        }
+        let mut position = t.position();
+        let mut rest = t.value();
+        while let Some(q) = self.quote()
+            && let Some(i) = rest.find('\n') {
+            let line = &rest[..i];
+            if !line.is_empty() {
+                self.text(line, position);
+            }
+            position = position.advance_str(line);
+            rest = &rest[i+1..];
+            if rest.is_empty() && self.next_is(MDTokensType::GreaterThan) {
+                // Skip the `>` of the next line.
+                self.tokens.next();
+                self.text("\n", position);
+                return;
+            }
+            if q != self.stack.len() - 1 || self.stack[q].kind == MDTokensType::DoubleStar {
+                // It is an error, the parser will be resynced.
+                return self.close_quote(q, position);
+            }
+            self.close_quote(q, position);
+            self.text("\n", position);
+            position = position.advance('\n');
+        }
+        if !rest.is_empty() {
+            self.text(rest, position);
+        }
+    }
+
+    /// Parse the Fixed Width Code, nothing is parsed inside it.
+    fn code(&mut self, open: &MDToken<'a>) {
+        let Some(Raw { value, end, backslashes }) = self.raw_until(MDTokensType::Backtick) else {
+            return self.unclosed_raw(MarkdownErrorType::BacktickOpen, open.position(), after(open));
+        };
+        self.backslashes(&backslashes);
+        if value.is_empty() {
+            return self.invalid(MarkdownErrorType::EmptyEntity, open.position(), open.position(), end);
+        }
+        self.add(Node::with_position(Markdown::FixedWidthCode(value), open.position()));
+    }
+
+    /// Parse the Pre-Fixed Width Code, nothing is parsed inside it.
+    fn pre(&mut self, open: &MDToken<'a>) {
+        let Some(Raw { value, end, backslashes }) = self.raw_until(MDTokensType::Backticks) else {
+            return self.unclosed_raw(MarkdownErrorType::BackticksOpen, open.position(), after(open));
+        };
+        self.backslashes(&backslashes);
+        // ```lang\n code \n```
+        let (lang, code) = match value.split_once('\n') {
+            Some((lang, code)) => (
+                if !lang.is_empty() { Some(lang.to_string()) } else { None },
+                code.to_string()
+            ),
+            None => (None, value)
+        };
+        if code.is_empty() {
+            return self.invalid(MarkdownErrorType::EmptyEntity, open.position(), open.position(), end);
+        }
+        self.add(Node::with_position(
+            Markdown::PreFormattedFixedWidthCode { lang, code },
+            open.position()
+        ));
+    }
+
+    /// Open the Inline URL title or the Custom Emoji, the `!` is followed by the `[` token.
+    /// The Inline URLs can not be nested.
+    fn url_open(&mut self, open: &MDToken<'a>) {
+        let end = if *open.token_type() == MDTokensType::ExclamationMark {
+            match self.tokens.next() {
+                Some(t) => after(t),
+                None => after(open)
+            }
+        } else {
+            after(open)
+        };
+        if self.stack.iter().any(|f| is_url(&f.kind)) {
+            return self.invalid(MarkdownErrorType::NestedInlineURL, open.position(), open.position(), end);
+        }
+        self.stack.push(Frame {
+            kind: open.token_type().clone(),
+            start: open.position(),
+            resync: end,
+            children: Vec::new()
+        });
+    }
+
+    /// Close the Inline URL title or the Custom Emoji and parse the URL.
+    fn url_close(&mut self, close: &MDToken<'a>) {
+        let Some(i) = self.stack.iter().rposition(|f| is_url(&f.kind)) else {
+            return self.unescaped(close);
+        };
+        if i != self.stack.len() - 1 {
+            return self.unclosed(i + 1, close.position());
+        }
+        let open = match self.tokens.next().cloned() {
+            Some(t) if *t.token_type() == MDTokensType::ParenthesesOpen => t,
+            next => {
+                // It is not Inline URL, the square brackets had to be escaped.
+                if next.is_some() {
+                    self.tokens.go_back();
+                }
+                let Some(frame) = self.stack.pop() else {
+                    return;
+                };
+                let mut position = frame.start;
+                if frame.kind == MDTokensType::ExclamationMark {
+                    self.report(MarkdownError::with_position(MarkdownErrorType::UnescapedCharacter('!'), position));
+                    self.text("!", position);
+                    position = position.advance('!');
+                }
+                self.report(MarkdownError::with_position(MarkdownErrorType::UnescapedCharacter('['), position));
+                self.text("[", position);
+                for child in frame.children {
+                    self.add(child);
+                }
+                return self.unescaped(close);
+            }
+        };
+        let Some(Raw { value: url, end, backslashes }) = self.raw_until(MDTokensType::ParenthesesClose) else {
+            let (start, expected) = (self.stack[i].start, self.end());
+            return self.fail(i, MarkdownErrorType::InlineURLOpen, start, expected, after(&open));
+        };
+        let Some(frame) = self.stack.pop() else {
+            return;
+        };
+        self.backslashes(&backslashes);
+        let title = visible_text(&frame.children);
+        let err = if title.is_empty() {
+            Some((MarkdownErrorType::EmptyEntity, frame.start))
+        } else if url.trim().is_empty() {
+            Some((MarkdownErrorType::EmptyURL, after(&open)))
+        } else if let Err(err) = url::validate(&url) {
+            Some((
+                MarkdownErrorType::InvalidURL(err.err().clone()),
+                self.source_position(after(&open), err.offset())
+            ))
+        } else if frame.kind == MDTokensType::ExclamationMark && url::custom_emoji_id(&url).is_none() {
+            Some((MarkdownErrorType::InvalidURL(url::URLErrorType::InvalidCustomEmojiId), after(&open)))
+        } else {
+            None
+        };
+        if let Some((err, position)) = err {
+            return self.invalid(err, position, frame.start, end);
+        }
+        let value = match url::custom_emoji_id(&url) {
+            Some(id) if frame.kind == MDTokensType::ExclamationMark => Markdown::CustomEmoji { emoji: title, id },
+            // The `tg://user?id=` URL is a Text Mention.
+            _ => Markdown::InlineURL { title, url }.to_text_mention()
+        };
+        self.add(entity(value, frame.start, frame.children));
+    }
+
+    /// Get the raw text until the `to` token, only the escapes are parsed.
+    fn raw_until(&mut self, to: MDTokensType) -> Option<Raw> {
+        let mut value = String::new();
+        let mut backslashes: Vec<Position> = Vec::new();
+        while let Some(t) = self.tokens.next() {
+            if *t.token_type() == MDTokensType::Escape {
+                if t.value().len() == 1 {
+                    backslashes.push(t.position());
+                }
+                value += escaped(t.value());
+            } else if *t.token_type() == to {
+                return Some(Raw { value, end: after(t), backslashes });
+            } else {
+                value += t.value();
+            }
+        }
+        return None;
+    }
+
+    /// Get the document position of the byte `offset` in the text that `raw_until`
+    /// returned for the content that starts from `start`.
+    fn source_position(&self, start: Position, offset: usize) -> Position {
+        let mut value_offset: usize = 0;
+        for t in mdv2::Lexer::from_position(self.document, start) {
+            let value = if *t.token_type() == MDTokensType::Escape { escaped(t.value()) } else { t.value() };
+            if value_offset + value.len() > offset {
+                if *t.token_type() == MDTokensType::Escape {
+                    return t.position();
+                }
+                return t.position().advance_str(&value[..offset-value_offset]);
+            }
+            value_offset += value.len();
+        }
+        return start;
+    }
+
+    /// Get the index of the open Blockquote frame.
+    fn quote(&self) -> Option<usize> {
+        return self.stack.iter().position(|f| matches!(
+            f.kind,
+            MDTokensType::GreaterThan | MDTokensType::DoubleStar
+        ));
+    }
+
+    /// Check if the position is at the start of a line.
+    fn line_start(&self, position: Position) -> bool {
+        return position.byte() == 0 || self.document[..position.byte()].ends_with('\n');
+    }
+
+    /// Check if the next token is of the `kind` type without consuming it.
+    fn next_is(&mut self, kind: MDTokensType) -> bool {
+        let is = matches!(self.tokens.next(), Some(t) if *t.token_type() == kind);
+        self.tokens.go_back();
+        return is;
+    }
+
+    /// Report the reserved chars of the token, they are kept in the AST as a text.
+    fn unescaped(&mut self, t: &MDToken<'a>) {
+        let mut position = t.position();
+        for c in t.value().chars() {
+            self.report(MarkdownError::with_position(MarkdownErrorType::UnescapedCharacter(c), position));
+            position = position.advance(c);
+        }
+        self.text(t.value(), t.position());
+    }
+
+    /// Report the backslashes that do not escape any char inside the code or the URL,
+    /// there the backslash itself must be escaped.
+    fn backslashes(&mut self, positions: &[Position]) {
+        for position in positions {
+            self.report(MarkdownError::with_position(MarkdownErrorType::UnescapedCharacter('\\'), *position));
+        }
+    }
+
+    /// Add a text to the current entity, It is merged with the previous text.
+    fn text(&mut self, text: &str, position: Position) {
+        let Some(frame) = self.stack.last_mut() else {
+            return;
+        };
+        if let Some(last) = frame.children.last_mut()
+            && last.inner().is_empty()
+            && let Markdown::Text(value) = last.value() {
+            *last = Node::with_position(Markdown::Text(format!("{}{}", value, text)), last.position());
+            return;
+        }
+        frame.children.push(Node::with_position(Markdown::Text(text.to_string()), position));
+    }
+
+    /// Add a node to the current entity.
+    fn add(&mut self, node: Node) {
+        if let Markdown::Text(text) = node.value()
+            && node.inner().is_empty() {
+            self.text(&text.clone(), node.position());
+            return;
+        }
+        if let Some(frame) = self.stack.last_mut() {
+            frame.children.push(node);
+        }
+    }
+
+    /// Get the end position of the document.
+    fn end(&mut self) -> Position {
+        return *self.end.get_or_insert_with(|| Position::default().advance_str(self.document));
+    }
+
+    /// Handle the unclosed entity of the frame `i`, the closing delimiter was expected
+    /// at `expected`.
+    fn unclosed(&mut self, i: usize, expected: Position) {
+        let frame = &self.stack[i];
+        let (start, resync) = (frame.start, frame.resync);
+        let err = match frame.kind {
+            MDTokensType::Star => MarkdownErrorType::StarOpen,
+            MDTokensType::Underscore => MarkdownErrorType::UnderscoreOpen,
+            MDTokensType::DoubleUnderscore => MarkdownErrorType::DoubleUnderscoreOpen,
+            MDTokensType::Tilde => MarkdownErrorType::TildeOpen,
+            MDTokensType::DoublePipe => MarkdownErrorType::DoublePipeOpen,
+            MDTokensType::DoubleStar => MarkdownErrorType::ExpandableBlockquoteOpen,
+            _ => MarkdownErrorType::SquareBracketsOpen
+        };
+        return self.fail(i, err, start, expected, resync);
+    }
+
+    /// Handle an unclosed Fixed Width Code or Pre-Fixed Width Code, the region
+    /// `start..resync` is an error.
+    fn unclosed_raw(&mut self, err: MarkdownErrorType, start: Position, resync: Position) {
+        let expected = self.end();
+        return self.fail(self.stack.len(), err, start, expected, resync);
+    }
+
+    /// Report the error, drop the frames from `i`, add an error node for the region
+    /// `start..resync` and continue parsing from `resync`.
+    fn fail(
+        &mut self,
+        i: usize,
+        err: MarkdownErrorType,
+        start: Position,
+        expected: Position,
+        resync: Position
+    ) {
+        self.report(MarkdownError::with_position(err, start).with_expected(expected));
+        self.stack.truncate(i);
+        self.add(Node::with_position(
+            Markdown::Error(self.document[start.byte()..resync.byte()].to_string()),
+            start
+        ));
+        self.tokens = IterReader::new(mdv2::Lexer::from_position(self.document, resync));
+    }
+
+    /// Handle a parsed but invalid entity in the region `start..end`, It reports the error
+    /// and adds an error node for the region.
+    fn invalid(
+        &mut self,
+        err: MarkdownErrorType,
+        position: Position,
+        start: Position,
+        end: Position
+    ) {
+        self.report(MarkdownError::with_position(err, position));
+        self.add(Node::with_position(
+            Markdown::Error(self.document[start.byte()..end.byte()].to_string()),
+            start
+        ));
+    }
+
+    /// Collect the error, the same error in a resynced region is reported once.
+    fn report(&mut self, error: MarkdownError) {
+        if !self.errors.iter().any(|e| e.position() == error.position() && e.err() == error.err()) {
+            self.errors.push(error);
+        }
+    }
+}
+
+/// Get the position after the token.
+fn after(t: &MDToken<'_>) -> Position {
+    return t.position().advance_str(t.value());
+}
+
+/// Check if the frame kind is an Inline URL title or a Custom Emoji.
+fn is_url(kind: &MDTokensType) -> bool {
+    return matches!(kind, MDTokensType::SquareBracketsOpen | MDTokensType::ExclamationMark);
+}
+
+/// Get the escaped char of the escape token, the lonely `\` is kept as it is.
+fn escaped(value: &str) -> &str {
+    if value.len() > 1 {
+        return &value[1..];
+    }
+    return value;
+}
+
+/// The Markdown V2 parser tests.
+#[cfg(test)]
+mod mdv2_parser_tests {
+    use super::{parse_all, parser};
+    use crate::parser::enums::{Markdown, MarkdownErrorType};
+    use crate::parser::types::Node;
+
+    fn values(node: &Node) -> Vec<&Markdown> {
+        return node.inner().iter().map(|n| n.value()).collect();
+    }
+
+    #[test]
+    fn flat() {
+        let ast = parser("*bold* _italic_ `code` ```rust\nlet x;``` [url](https://example.com)").unwrap();
+        assert_eq!(values(&ast), vec![
+            &Markdown::Bold("bold".to_string()),
+            &Markdown::Text(" ".to_string()),
+            &Markdown::Italic("italic".to_string()),
+            &Markdown::Text(" ".to_string()),
+            &Markdown::FixedWidthCode("code".to_string()),
+            &Markdown::Text(" ".to_string()),
+            &Markdown::PreFormattedFixedWidthCode { lang: Some("rust".to_string()), code: "let x;".to_string() },
+            &Markdown::Text(" ".to_string()),
+            &Markdown::InlineURL { title: "url".to_string(), url: "https://example.com".to_string() },
+        ]);
+        assert!(ast.inner().iter().all(|n| n.inner().is_empty()));
+    }
+
+    #[test]
+    fn nested() {
+        let ast = parser("_a *b [c](https://e.com)* f_").unwrap();
+        let italic = &ast.inner()[0];
+        assert_eq!(italic.value(), &Markdown::Italic("a b c f".to_string()));
+        let bold = &italic.inner()[1];
+        assert_eq!(bold.value(), &Markdown::Bold("b c".to_string()));
+        assert_eq!(values(bold), vec![
+            &Markdown::Text("b ".to_string()),
+            &Markdown::InlineURL { title: "c".to_string(), url: "https://e.com".to_string() },
+        ]);
+        let ast = parser("[c *d*](https://e.com)").unwrap();
+        assert_eq!(values(&ast.inner()[0]), vec![
+            &Markdown::Text("c ".to_string()),
+            &Markdown::Bold("d".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn code_is_raw() {
+        let ast = parser("*a `*b_ \\` \\\\`*").unwrap();
+        assert_eq!(values(&ast.inner()[0]), vec![
+            &Markdown::Text("a ".to_string()),
+            &Markdown::FixedWidthCode("*b_ ` \\".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn escapes() {
+        let ast = parser("\\*a\\_ \\[b\\] *c\\*d*").unwrap();
+        assert_eq!(values(&ast), vec![
+            &Markdown::Text("*a_ [b] ".to_string()),
+            &Markdown::Bold("c*d".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn nested_url() {
+        let err = parser("[a [b](https://b.com)](https://a.com)").unwrap_err();
+        assert_eq!(err.err(), &MarkdownErrorType::NestedInlineURL);
+        assert_eq!(err.offset(), 3);
+    }
+
+    #[test]
+    fn url_escapes() {
+        let ast = parser("[a](https://a.com/\\)x\\\\)").unwrap();
+        assert_eq!(values(&ast), vec![
+            &Markdown::InlineURL { title: "a".to_string(), url: "https://a.com/)x\\".to_string() },
+        ]);
+        let ast = parser("[name](tg://user?id=42)").unwrap();
+        assert_eq!(values(&ast), vec![
+            &Markdown::TextMention { name: "name".to_string(), user_id: 42 },
+        ]);
+    }
+
+    #[test]
+    fn double_star() {
+        let ast = parser("*a**b*").unwrap();
+        assert_eq!(values(&ast), vec![
+            &Markdown::Bold("a".to_string()),
+            &Markdown::Bold("b".to_string()),
+        ]);
+        assert_eq!(parser("**").unwrap_err().err(), &MarkdownErrorType::EmptyEntity);
+    }
+
+    #[test]
+    fn recovery() {
+        let (ast, errors) = parse_all("*a _b* c [d](e f) `g");
+        let errs: Vec<(&MarkdownErrorType, usize)> = errors.iter().map(|e| (e.err(), e.offset())).collect();
+        assert_eq!(errs, vec![
+            (&MarkdownErrorType::UnderscoreOpen, 3),
+            (&MarkdownErrorType::InvalidURL(crate::parser::url::URLErrorType::Whitespace), 14),
+            (&MarkdownErrorType::BacktickOpen, 18),
+        ]);
+        assert_eq!(values(&ast), vec![
+            &Markdown::Bold("a _b".to_string()),
+            &Markdown::Text(" c ".to_string()),
+            &Markdown::Error("[d](e f)".to_string()),
+            &Markdown::Text(" ".to_string()),
+            &Markdown::Error("`".to_string()),
+            &Markdown::Text("g".to_string()),
+        ]);
+        assert_eq!(ast.inner()[0].inner()[1].value(), &Markdown::Error("_".to_string()));
+    }
+
+    #[test]
+    fn unclosed_at_end() {
+        let (_, errors) = parse_all("*a _b [c");
+        let errs: Vec<(&MarkdownErrorType, usize)> = errors.iter().map(|e| (e.err(), e.offset())).collect();
+        assert_eq!(errs, vec![
+            (&MarkdownErrorType::StarOpen, 0),
+            (&MarkdownErrorType::UnderscoreOpen, 3),
+            (&MarkdownErrorType::SquareBracketsOpen, 6),
+        ]);
+        assert_eq!(parser("*a _b [c").unwrap_err().err(), &MarkdownErrorType::StarOpen);
+    }
+
+    #[test]
+    fn resynced_errors_once() {
+        let (_, errors) = parse_all("*a _b. *c");
+        let errs: Vec<(&MarkdownErrorType, usize)> = errors.iter().map(|e| (e.err(), e.offset())).collect();
+        assert_eq!(errs, vec![
+            (&MarkdownErrorType::UnderscoreOpen, 3),
+            (&MarkdownErrorType::UnescapedCharacter('.'), 5),
+        ]);
+    }
+
+    #[test]
+    fn unescaped() {
+        let (ast, errors) = parse_all("a.b - *c!* [d] \\.");
+        let errs: Vec<(&MarkdownErrorType, usize)> = errors.iter().map(|e| (e.err(), e.offset())).collect();
+        assert_eq!(errs, vec![
+            (&MarkdownErrorType::UnescapedCharacter('.'), 1),
+            (&MarkdownErrorType::UnescapedCharacter('-'), 4),
+            (&MarkdownErrorType::UnescapedCharacter('!'), 8),
+            (&MarkdownErrorType::UnescapedCharacter('['), 11),
+            (&MarkdownErrorType::UnescapedCharacter(']'), 13),
+        ]);
+        assert_eq!(values(&ast), vec![
+            &Markdown::Text("a.b - ".to_string()),
+            &Markdown::Bold("c!".to_string()),
+            &Markdown::Text(" [d] .".to_string()),
+        ]);
+        assert_eq!(parser("1. a").unwrap_err().err(), &MarkdownErrorType::UnescapedCharacter('.'));
+    }
+
+    #[test]
+    fn unescaped_in_code_and_url() {
+        assert!(parser("`a.b-c*_[` ```\n#{}|``` [a](https://a.com/a.b-c_(d)").is_ok());
+        let (_, errors) = parse_all("`a\\é` [a](https://a.com/\\é)");
+        let errs: Vec<(&MarkdownErrorType, usize)> = errors.iter().map(|e| (e.err(), e.offset())).collect();
+        assert_eq!(errs, vec![
+            (&MarkdownErrorType::UnescapedCharacter('\\'), 2),
+            (&MarkdownErrorType::UnescapedCharacter('\\'), 24),
+        ]);
+    }
+
+    #[test]
+    fn blockquote() {
+        let ast = parser(">first *bold\n>line*\n>\n>last\nafter").unwrap();
+        let quote = &ast.inner()[0];
+        assert_eq!(quote.value(), &Markdown::Blockquote("first bold\nline\n\nlast".to_string()));
+        assert_eq!(values(quote), vec![
+            &Markdown::Text("first ".to_string()),
+            &Markdown::Bold("bold\nline".to_string()),
+            &Markdown::Text("\n\nlast".to_string()),
+        ]);
+        assert_eq!(ast.inner()[1].value(), &Markdown::Text("\nafter".to_string()));
+        let ast = parser("a\n>b").unwrap();
+        assert_eq!(values(&ast), vec![
+            &Markdown::Text("a\n".to_string()),
+            &Markdown::Blockquote("b".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn expandable_blockquote() {
+        let ast = parser("**>shown\n>hidden||\nafter").unwrap();
+        assert_eq!(values(&ast), vec![
+            &Markdown::ExpandableBlockquote("shown\nhidden".to_string()),
+            &Markdown::Text("\nafter".to_string()),
+        ]);
+        let err = parser("**>shown\n>hidden\nafter").unwrap_err();
+        assert_eq!(err.err(), &MarkdownErrorType::ExpandableBlockquoteOpen);
+        assert_eq!(err.expected().unwrap().char(), 16);
+        let ast = parser("**>a ||b||||").unwrap();
+        assert_eq!(values(&ast.inner()[0]), vec![
+            &Markdown::Text("a ".to_string()),
+            &Markdown::Spoiler("b".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn blockquote_errors() {
+        let (_, errors) = parse_all("a **>b\n*c\n>d*\n>e *f\ng");
+        let errs: Vec<(&MarkdownErrorType, usize)> = errors.iter().map(|e| (e.err(), e.offset())).collect();
+        assert_eq!(errs, vec![
+            (&MarkdownErrorType::BlockquoteNotAtLineStart, 2),
+            (&MarkdownErrorType::BlockquoteInEntity, 10),
+            (&MarkdownErrorType::StarOpen, 17),
+        ]);
+        assert_eq!(parser("a > b").unwrap_err().err(), &MarkdownErrorType::UnescapedCharacter('>'));
+    }
+
+    #[test]
+    fn underline_strikethrough_spoiler() {
+        let ast = parser("__u__ ~s~ ||p|| ~*a ||b||*~").unwrap();
+        assert_eq!(values(&ast), vec![
+            &Markdown::Underline("u".to_string()),
+            &Markdown::Text(" ".to_string()),
+            &Markdown::Strikethrough("s".to_string()),
+            &Markdown::Text(" ".to_string()),
+            &Markdown::Spoiler("p".to_string()),
+            &Markdown::Text(" ".to_string()),
+            &Markdown::Strikethrough("a b".to_string()),
+        ]);
+        let (_, errors) = parse_all("__u ~s ||p");
+        let errs: Vec<(&MarkdownErrorType, usize)> = errors.iter().map(|e| (e.err(), e.offset())).collect();
+        assert_eq!(errs, vec![
+            (&MarkdownErrorType::DoubleUnderscoreOpen, 0),
+            (&MarkdownErrorType::TildeOpen, 4),
+            (&MarkdownErrorType::DoublePipeOpen, 7),
+        ]);
+    }
+
+    #[test]
+    fn italic_underline() {
+        let ast = parser("___italic underline_\r__").unwrap();
+        let underline = &ast.inner()[0];
+        assert_eq!(underline.value(), &Markdown::Underline("italic underline".to_string()));
+        assert_eq!(values(underline), vec![&Markdown::Italic("italic underline".to_string())]);
+        assert_eq!(parser("___italic underline___").unwrap_err().err(), &MarkdownErrorType::UnderscoreOpen);
+    }
+
+    #[test]
+    fn custom_emoji() {
+        let ast = parser("*a ![👍](tg://emoji?id=5368324170671202286)*").unwrap();
+        assert_eq!(values(&ast.inner()[0]), vec![
+            &Markdown::Text("a ".to_string()),
+            &Markdown::CustomEmoji { emoji: "👍".to_string(), id: 5368324170671202286 },
+        ]);
+        let (_, errors) = parse_all("![👍](https://a.com) ![👍](tg://emoji?id=x) !a");
+        let errs: Vec<(&MarkdownErrorType, usize)> = errors.iter().map(|e| (e.err(), e.offset())).collect();
+        assert_eq!(errs, vec![
+            (&MarkdownErrorType::InvalidURL(crate::parser::url::URLErrorType::InvalidCustomEmojiId), 5),
+            (&MarkdownErrorType::InvalidURL(crate::parser::url::URLErrorType::InvalidCustomEmojiId), 39),
+            (&MarkdownErrorType::UnescapedCharacter('!'), 42),
+        ]);
+    }
+}
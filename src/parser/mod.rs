@@ -1,3 +1,10 @@
 pub mod mdv1;
+pub mod mdv2;
+pub mod html;
+pub mod sanitize;
+pub mod entities;
+pub mod cst;
+pub mod url;
+pub mod diagnostic;
 pub mod types;
 pub mod enums;
\ No newline at end of file
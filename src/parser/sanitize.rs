@@ -0,0 +1,552 @@
+use crate::lexer::types::Position;
+use crate::render::html::escape;
+
+use super::html::{reference, Tag, TagReader};
+use super::url;
+
+/// The tags that are removed with their content.
+const DROPPED: [&str; 9] = ["script", "style", "head", "title", "template", "iframe", "object", "svg", "noscript"];
+
+/// The tags that have no content and no closing tag.
+const VOID: [&str; 10] = ["br", "hr", "img", "input", "meta", "link", "wbr", "source", "area", "embed"];
+
+/// The block tags, they are separated from the text around them by an empty line.
+const BLOCKS: [&str; 18] = [
+    "p", "div", "h1", "h2", "h3", "h4", "h5", "h6", "section", "article",
+    "header", "footer", "nav", "aside", "main", "table", "figure", "address"
+];
+
+/// The named HTML entities that the sanitizer decodes in addition to the Telegram ones.
+const EXTRA_ENTITIES: [(&str, char); 16] = [
+    ("nbsp", '\u{a0}'), ("apos", '\''), ("copy", '©'), ("reg", '®'), ("trade", '™'), ("hellip", '…'),
+    ("mdash", '—'), ("ndash", '–'), ("laquo", '«'), ("raquo", '»'), ("lsquo", '‘'), ("rsquo", '’'),
+    ("ldquo", '“'), ("rdquo", '”'), ("bull", '•'), ("euro", '€')
+];
+
+/// The lost content types.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LossType {
+    /// An unsupported tag that was removed, its content is kept, for example: `<span style="color: red">`.
+    Tag(String),
+    /// A tag that was removed with its content, for example: `<script>`.
+    Content(String),
+    /// A link without a valid URL, its text is kept, for example: `<a href="javascript:void(0)">`.
+    Link(String),
+    /// An unknown HTML entity that was removed, for example: `&zwj;`.
+    Entity(String)
+}
+
+/// The content that the sanitizer removed, the position is where it starts in the source.
+/// 
+/// ### Example:
+/// ```
+/// use telemark::parser::sanitize::{sanitize, LossType};
+/// 
+/// let (_, losses) = sanitize("<font color=red>red</font>");
+/// assert_eq!(losses[0].loss(), &LossType::Tag("font".to_string()));
+/// assert_eq!(losses[0].offset(), 0);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Loss {
+    loss: LossType,
+    position: Position
+}
+impl Loss {
+    /// Create new `Loss`.
+    pub fn new(loss: LossType, position: Position) -> Self {
+        return Self {
+            loss,
+            position
+        };
+    }
+
+    /// Get the loss type.
+    pub fn loss(&self) -> &LossType {
+        return &self.loss;
+    }
+
+    /// Get the loss offset in chars.
+    pub fn offset(&self) -> usize {
+        return self.position.char();
+    }
+
+    /// Get the loss position in bytes, chars and UTF-16 code units.
+    pub fn position(&self) -> Position {
+        return self.position;
+    }
+}
+
+/// Sanitize any HTML to the Telegram HTML subset. The supported tags are mapped to their
+/// Telegram equivalents, the paragraphs, the line breaks and the lists become newlines and
+/// bullets, and the unsupported tags are removed. It returns the Telegram HTML and the
+/// report of the removed content.
+/// 
+/// ### Example:
+/// ```
+/// use telemark::parser::{html, sanitize::{sanitize, LossType}};
+/// 
+/// let (output, losses) = sanitize(concat!(
+///     "<h1>Title</h1>",
+///     "<p>Hello <strong>world</strong>!</p>",
+///     "<ul><li>one</li><li>two</li></ul>",
+///     "<script>alert(1)</script>"
+/// ));
+/// assert_eq!(output, "<b>Title</b>\n\nHello <b>world</b>!\n\n• one\n• two");
+/// assert_eq!(losses[0].loss(), &LossType::Content("script".to_string()));
+/// assert!(html::parser(&output).is_ok());
+/// ```
+pub fn sanitize(document: &str) -> (String, Vec<Loss>) {
+    let mut sanitizer = Sanitizer {
+        document,
+        reader: TagReader::from_position(document, Position::default()),
+        output: String::new(),
+        stack: Vec::new(),
+        lists: Vec::new(),
+        losses: Vec::new(),
+        newlines: 2,
+        space: true
+    };
+    sanitizer.sanitize();
+    return (sanitizer.output, sanitizer.losses);
+}
+
+/// An open source tag.
+struct Open {
+    /// The lowercase source tag name.
+    name: String,
+    /// The Telegram closing tag, It is empty if the tag was removed.
+    close: &'static str,
+    /// The output length before the Telegram opening tag.
+    start: usize,
+    /// The output length after the Telegram opening tag.
+    content: usize
+}
+
+/// The sanitizer state.
+struct Sanitizer<'a> {
+    document: &'a str,
+    reader: TagReader<'a>,
+    output: String,
+    stack: Vec<Open>,
+    /// The open lists, the ordered lists have the last item number.
+    lists: Vec<Option<usize>>,
+    losses: Vec<Loss>,
+    /// The number of the newlines at the end of the output text.
+    newlines: usize,
+    /// The output text ends with a whitespace.
+    space: bool
+}
+impl<'a> Sanitizer<'a> {
+    /// Sanitize the document.
+    fn sanitize(&mut self) {
+        while self.reader.peek_char().is_some() {
+            if self.declaration_start() {
+                self.declaration();
+                continue;
+            }
+            if !self.reader.tag_start() {
+                self.text();
+                continue;
+            }
+            let start = self.reader.next_position();
+            match self.reader.tag() {
+                Some(tag) if tag.closing => self.close(&tag.name),
+                Some(tag) => self.open(tag),
+                None => {
+                    // It is not a tag, the `<` is a text.
+                    self.reader = TagReader::from_position(self.document, start.advance('<'));
+                    self.push('<');
+                }
+            }
+        }
+        while let Some(open) = self.stack.pop() {
+            self.end(open);
+        }
+        let trimmed = self.output.trim_end().len();
+        self.output.truncate(trimmed);
+    }
+
+    /// Read the text until the next tag, the whitespaces are collapsed outside the pre.
+    fn text(&mut self) {
+        let start = self.reader.next_position();
+        self.reader.next_char();
+        while self.reader.peek_char().is_some() && !self.reader.tag_start() && !self.declaration_start() {
+            self.reader.next_char();
+        }
+        let raw = &self.document[start.byte()..self.reader.next_position().byte()];
+        let pre = self.stack.iter().any(|o| o.name == "pre");
+        let mut position = start;
+        let mut rest = raw;
+        while let Some(c) = rest.chars().next() {
+            let len = match reference(rest) {
+                Some((Some(decoded), len)) => {
+                    self.push(decoded);
+                    len
+                },
+                Some((None, len)) => {
+                    let name = &rest[1..len-1];
+                    match EXTRA_ENTITIES.iter().find(|(n, _)| *n == name) {
+                        Some((_, decoded)) => self.push(*decoded),
+                        None => self.losses.push(Loss::new(LossType::Entity(rest[..len].to_string()), position))
+                    }
+                    len
+                },
+                None if pre => {
+                    self.push(c);
+                    c.len_utf8()
+                },
+                None if matches!(c, ' ' | '\t' | '\n' | '\r' | '\x0c') => {
+                    if !self.space {
+                        self.output.push(' ');
+                        self.space = true;
+                    }
+                    c.len_utf8()
+                },
+                None => {
+                    self.push(c);
+                    c.len_utf8()
+                }
+            };
+            position = position.advance_str(&rest[..len]);
+            rest = &rest[len..];
+        }
+    }
+
+    /// Add a text char to the output.
+    fn push(&mut self, c: char) {
+        if c == '\n' {
+            self.newline();
+            return;
+        }
+        self.output += &escape(&c.to_string());
+        self.newlines = 0;
+        self.space = c.is_whitespace();
+    }
+
+    /// Add a newline to the output, the space before it is removed outside the pre.
+    fn newline(&mut self) {
+        if self.output.ends_with(' ') && !self.stack.iter().any(|o| o.name == "pre") {
+            self.output.pop();
+        }
+        self.output.push('\n');
+        self.newlines += 1;
+        self.space = true;
+    }
+
+    /// Start a new line if the output does not end with a newline.
+    fn line(&mut self) {
+        if self.newlines == 0 {
+            self.newline();
+        }
+    }
+
+    /// Start a new paragraph, It is separated by an empty line.
+    fn paragraph(&mut self) {
+        while self.newlines < 2 {
+            self.newline();
+        }
+    }
+
+    /// Separate the block from the text around it by an empty line, It is a new line in a
+    /// list item, and nothing at the start of a list item or a Blockquote.
+    fn block(&mut self) {
+        if self.stack.last().is_some_and(|o| matches!(o.name.as_str(), "li" | "blockquote") && o.content == self.output.len()) {
+            return;
+        }
+        if self.stack.iter().any(|o| o.name == "li") {
+            self.line();
+        } else {
+            self.paragraph();
+        }
+    }
+
+    /// Open the source tag.
+    fn open(&mut self, tag: Tag<'a>) {
+        let name = tag.name.as_str();
+        if DROPPED.contains(&name) {
+            self.skip(&tag);
+            return;
+        }
+        match name {
+            "br" => return self.newline(),
+            "hr" => return self.paragraph(),
+            _ if VOID.contains(&name) => {
+                if name == "img" {
+                    self.losses.push(Loss::new(LossType::Content(tag.name.clone()), tag.start));
+                }
+                return;
+            },
+            "ul" => {
+                self.line();
+                self.lists.push(None);
+            },
+            "ol" => {
+                self.line();
+                self.lists.push(Some(0));
+            },
+            "li" => {
+                self.line();
+                let indent = "  ".repeat(self.lists.len().saturating_sub(1));
+                let bullet = match self.lists.last_mut() {
+                    Some(Some(n)) => {
+                        *n += 1;
+                        format!("{}{}. ", indent, n)
+                    },
+                    _ => format!("{}• ", indent)
+                };
+                for c in bullet.chars() {
+                    self.push(c);
+                }
+            },
+            "blockquote" => self.line(),
+            _ if BLOCKS.contains(&name) => self.block(),
+            _ => ()
+        }
+        let (open, close) = self.telegram(&tag);
+        let start = self.output.len();
+        self.output += &open;
+        self.stack.push(Open {
+            name: tag.name,
+            close,
+            start,
+            content: self.output.len()
+        });
+    }
+
+    /// Get the Telegram opening and closing tags of the source tag, they are empty
+    /// if the tag is removed.
+    fn telegram(&mut self, tag: &Tag<'a>) -> (String, &'static str) {
+        let in_code = self.stack.iter().any(|o| o.close == "</code>" || o.close == "</pre>");
+        let pre_code = tag.name == "code"
+            && self.stack.last().is_some_and(|o| o.close == "</pre>" && o.content == self.output.len());
+        let (open, close) = match tag.name.as_str() {
+            _ if in_code && !pre_code => (String::new(), ""),
+            "b" | "strong" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => ("<b>".to_string(), "</b>"),
+            "i" | "em" => ("<i>".to_string(), "</i>"),
+            "u" | "ins" => ("<u>".to_string(), "</u>"),
+            "s" | "strike" | "del" => ("<s>".to_string(), "</s>"),
+            "tg-spoiler" => ("<tg-spoiler>".to_string(), "</tg-spoiler>"),
+            "span" if tag.attribute("class").is_some_and(|c| c.value.split_whitespace().any(|c| c == "tg-spoiler")) => (
+                "<tg-spoiler>".to_string(), "</tg-spoiler>"
+            ),
+            "code" if pre_code => match tag.attribute("class").and_then(|c| c.value.strip_prefix("language-")) {
+                Some(lang) if !lang.is_empty() => (format!("<code class=\"language-{}\">", escape(lang)), "</code>"),
+                _ => ("<code>".to_string(), "</code>")
+            },
+            "code" => ("<code>".to_string(), "</code>"),
+            "pre" => ("<pre>".to_string(), "</pre>"),
+            "a" if self.stack.iter().any(|o| o.close == "</a>") => (String::new(), ""),
+            "a" => {
+                let href = tag.attribute("href").map(|h| decode(h.value)).unwrap_or_default();
+                if href.trim().is_empty() || url::validate(&href).is_err() {
+                    self.losses.push(Loss::new(LossType::Link(href), tag.start));
+                    return (String::new(), "");
+                }
+                (format!("<a href=\"{}\">", escape(&href)), "</a>")
+            },
+            "blockquote" if self.stack.iter().any(|o| !o.close.is_empty()) => (String::new(), ""),
+            "blockquote" if tag.attribute("expandable").is_some() => ("<blockquote expandable>".to_string(), "</blockquote>"),
+            "blockquote" => ("<blockquote>".to_string(), "</blockquote>"),
+            "tg-emoji" => match tag.attribute("emoji-id").and_then(|id| id.value.parse::<u64>().ok()) {
+                Some(id) if id > 0 => (format!("<tg-emoji emoji-id=\"{}\">", id), "</tg-emoji>"),
+                _ => (String::new(), "")
+            },
+            // The lists are written as bullets.
+            "ul" | "ol" | "li" => return (String::new(), ""),
+            _ => (String::new(), "")
+        };
+        if close.is_empty() && !BLOCKS.contains(&tag.name.as_str()) {
+            self.losses.push(Loss::new(LossType::Tag(tag.name.clone()), tag.start));
+        }
+        return (open, close);
+    }
+
+    /// Close the last open tag with the name, the tags inside it are closed first.
+    fn close(&mut self, name: &str) {
+        let Some(i) = self.stack.iter().rposition(|o| o.name == name) else {
+            return;
+        };
+        while self.stack.len() > i {
+            if let Some(open) = self.stack.pop() {
+                self.end(open);
+            }
+        }
+    }
+
+    /// Write the Telegram closing tag of the open tag, the empty entities are removed
+    /// and the block separators at the end of the entity are moved after it.
+    fn end(&mut self, open: Open) {
+        if !open.close.is_empty() {
+            let end = if open.name == "pre" || self.stack.iter().any(|o| o.name == "pre") {
+                self.output.len()
+            } else {
+                self.output.trim_end_matches('\n').len().max(open.content)
+            };
+            let separators = self.output.split_off(end);
+            if self.output.len() == open.content {
+                self.output.truncate(open.start);
+            } else {
+                self.output += open.close;
+            }
+            self.output += &separators;
+        }
+        match open.name.as_str() {
+            "ul" | "ol" => {
+                self.lists.pop();
+                self.line();
+            },
+            "li" | "blockquote" => self.line(),
+            name if BLOCKS.contains(&name) => self.block(),
+            _ => ()
+        }
+    }
+
+    /// Check if the reader is at the start of a comment, a CDATA section or a declaration.
+    fn declaration_start(&self) -> bool {
+        return self.document[self.reader.next_position().byte()..].starts_with("<!");
+    }
+
+    /// Skip the comment, the CDATA section or the declaration like `<!DOCTYPE html>`,
+    /// the unclosed one is skipped until the end of the document.
+    fn declaration(&mut self) {
+        let start = self.reader.next_position();
+        let rest = &self.document[start.byte()..];
+        let end = [("<!--", "-->"), ("<![CDATA[", "]]>"), ("<!", ">")].iter()
+            .find(|(open, _)| rest.starts_with(open))
+            .and_then(|(open, close)| rest[open.len()..].find(close).map(|i| open.len() + i + close.len()))
+            .unwrap_or(rest.len());
+        self.reader = TagReader::from_position(self.document, start.advance_str(&rest[..end]));
+    }
+
+    /// Skip the tag and its content until its closing tag.
+    fn skip(&mut self, tag: &Tag<'a>) {
+        self.losses.push(Loss::new(LossType::Content(tag.name.clone()), tag.start));
+        let rest = self.document[tag.end.byte()..].to_ascii_lowercase();
+        let end = match rest.find(&format!("</{}", tag.name)) {
+            Some(i) => tag.end.advance_str(&self.document[tag.end.byte()..tag.end.byte()+i]),
+            None => tag.end.advance_str(&self.document[tag.end.byte()..])
+        };
+        self.reader = TagReader::from_position(self.document, end);
+        if self.reader.tag_start() {
+            self.reader.tag();
+        }
+    }
+}
+
+/// Decode the HTML entities of the attribute value, the unknown entities are kept.
+fn decode(raw: &str) -> String {
+    let mut value = String::new();
+    let mut rest = raw;
+    while let Some(c) = rest.chars().next() {
+        let len = match reference(rest) {
+            Some((Some(decoded), len)) => {
+                value.push(decoded);
+                len
+            },
+            Some((None, len)) => {
+                value += &rest[..len];
+                len
+            },
+            None => {
+                value.push(c);
+                c.len_utf8()
+            }
+        };
+        rest = &rest[len..];
+    }
+    return value;
+}
+
+/// The HTML sanitizer tests.
+#[cfg(test)]
+mod sanitize_tests {
+    use super::{sanitize, LossType};
+    use crate::parser::html;
+
+    fn losses(document: &str) -> Vec<(LossType, usize)> {
+        return sanitize(document).1.into_iter().map(|l| (l.loss().clone(), l.offset())).collect();
+    }
+
+    #[test]
+    fn editor_html() {
+        let document = concat!(
+            "<h2>Title</h2>\n",
+            "<p>Hello <em>world</em> &amp; <span style=\"color:red\">red</span>&nbsp;!</p>\n",
+            "<ul>\n  <li>one</li>\n  <li>two\n    <ol><li>a</li><li>b</li></ol>\n  </li>\n</ul>\n",
+            "<div>x<br>y</div><hr><p><a href=\"https://a.com/?a=1&amp;b=2\">link</a></p>"
+        );
+        let (output, _) = sanitize(document);
+        assert_eq!(output, concat!(
+            "<b>Title</b>\n\n",
+            "Hello <i>world</i> &amp; red\u{a0}!\n\n",
+            "• one\n• two\n  1. a\n  2. b\n\n",
+            "x\ny\n\n",
+            "<a href=\"https://a.com/?a=1&amp;b=2\">link</a>"
+        ));
+        assert!(html::parser(&output).is_ok());
+        assert_eq!(losses(document), vec![(LossType::Tag("span".to_string()), 45)]);
+    }
+
+    #[test]
+    fn loss_report() {
+        let document = concat!(
+            "<code><b>x</b></code> <a href=\"javascript:alert(1)\">bad</a> ",
+            "<a href=\"https://a.com\"><a href=\"https://b.com\">n</a></a>",
+            "<b></b>&zwj;<style>p {}</style><img src=\"a.png\">"
+        );
+        let (output, _) = sanitize(document);
+        assert_eq!(output, "<code>x</code> bad <a href=\"https://a.com\">n</a>");
+        assert!(html::parser(&output).is_ok());
+        assert_eq!(losses(document), vec![
+            (LossType::Tag("b".to_string()), 6),
+            (LossType::Link("javascript:alert(1)".to_string()), 22),
+            (LossType::Tag("a".to_string()), 84),
+            (LossType::Entity("&zwj;".to_string()), 124),
+            (LossType::Content("style".to_string()), 129),
+            (LossType::Content("img".to_string()), 148),
+        ]);
+    }
+
+    #[test]
+    fn telegram_html() {
+        for document in [
+            "<b>a <i>b</i></b> <u>c</u> <s>d</s> <tg-spoiler>e</tg-spoiler>",
+            "<pre><code class=\"language-rust\">fn a() {\n    x\n}</code></pre>",
+            "<blockquote expandable>q</blockquote>",
+            "<tg-emoji emoji-id=\"5368324170671202286\">👍</tg-emoji>",
+        ] {
+            assert_eq!(sanitize(document), (document.to_string(), Vec::new()), "in {:?}", document);
+        }
+    }
+
+    #[test]
+    fn broken_html() {
+        let (output, losses) = sanitize("<b>a <i>b</b> c</i> 1 < 2 <p");
+        assert_eq!(output, "<b>a <i>b</i></b> c 1 &lt; 2 &lt;p");
+        assert!(losses.is_empty());
+        assert!(html::parser(&output).is_ok());
+    }
+
+    #[test]
+    fn declarations() {
+        assert_eq!(sanitize("x<!-- <b> -->y"), ("xy".to_string(), Vec::new()));
+        assert_eq!(
+            sanitize("<!DOCTYPE html><p>a<![CDATA[<i>]]></p><!-- unclosed <b>"),
+            ("a".to_string(), Vec::new())
+        );
+    }
+
+    #[test]
+    fn nested_blocks() {
+        for (document, expected) in [
+            ("<ul><li><p>a</p></li><li><p>b</p><p>c</p></li></ul>", "• a\n• b\nc"),
+            ("<blockquote><p>a</p><p>b</p></blockquote><p>c</p>", "<blockquote>a\n\nb</blockquote>\n\nc"),
+            ("<b><div>a</div></b>b", "<b>a</b>\n\nb"),
+            ("<pre>a  \nb\n</pre>", "<pre>a  \nb\n</pre>"),
+        ] {
+            let (output, _) = sanitize(document);
+            assert_eq!(output, expected, "in {:?}", document);
+            assert!(html::parser(&output).is_ok());
+        }
+    }
+}
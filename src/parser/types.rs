@@ -1,3 +1,6 @@
+use std::fmt;
+
+use crate::lexer::types::Position;
 use super::enums::{Markdown, MarkdownErrorType};
 
 /// The main tree of the Markdown parser.
@@ -21,17 +24,41 @@ use super::enums::{Markdown, MarkdownErrorType};
 ///     )
 /// );
 /// ```
-#[derive(Debug, Clone, PartialEq)]
+/// 
+/// Two nodes are equal when their values and inner lists are equal, 
+/// the positions are not compared.
+#[derive(Debug, Clone)]
 pub struct Node {
     value: Markdown,
-    inner: Vec<Node>
+    inner: Vec<Node>,
+    position: Position
 }
 impl Node {
     /// Create new `Node`.
     pub fn new(value: Markdown) -> Self {
         return Self {
             value,
-            inner: Vec::new()
+            inner: Vec::new(),
+            position: Position::default()
+        };
+    }
+
+    /// Create new `Node` that starts at `position` in the document.
+    /// 
+    /// ### Example:
+    /// ```
+    /// use telemark::parser::types::Node;
+    /// use telemark::parser::enums::Markdown;
+    /// use telemark::lexer::types::Position;
+    /// 
+    /// let node = Node::with_position(Markdown::Bold("b".to_string()), Position::new(4, 1, 2));
+    /// assert_eq!(node.position().utf16(), 2);
+    /// ```
+    pub fn with_position(value: Markdown, position: Position) -> Self {
+        return Self {
+            value,
+            inner: Vec::new(),
+            position
         };
     }
 
@@ -86,6 +113,48 @@ impl Node {
     pub fn inner(&self) -> &Vec<Node> {
         return &self.inner;
     }
+
+    /// Get the node position, It is the position of the first char
+    /// of the node in the document.
+    /// 
+    /// ### Example:
+    /// ```
+    /// use telemark::parser::mdv1;
+    /// 
+    /// let ast = mdv1::parser("😀 *bold*").unwrap();
+    /// let bold = &ast.inner()[1];
+    /// assert_eq!(bold.position().byte(), 5);
+    /// assert_eq!(bold.position().char(), 2);
+    /// assert_eq!(bold.position().utf16(), 3);
+    /// ```
+    pub fn position(&self) -> Position {
+        return self.position;
+    }
+}
+impl PartialEq for Node {
+    fn eq(&self, other: &Self) -> bool {
+        return self.value == other.value && self.inner == other.inner;
+    }
+}
+
+/// Get the visible text of the nodes.
+pub(crate) fn visible_text(nodes: &[Node]) -> String {
+    return nodes.iter().map(|n| n.value().text()).collect();
+}
+
+/// Create the entity node, the children are added as inner nodes if they
+/// are not just a plain text.
+pub(crate) fn entity(value: Markdown, start: Position, children: Vec<Node>) -> Node {
+    let mut node = Node::with_position(value, start);
+    let plain = children.len() == 1
+        && children[0].inner().is_empty()
+        && matches!(children[0].value(), Markdown::Text(_));
+    if !plain {
+        for child in children {
+            node.add_inner(child);
+        }
+    }
+    return node;
 }
 
 
@@ -103,17 +172,53 @@ impl Node {
 #[derive(Debug, PartialEq)]
 pub struct MarkdownError {
     err: MarkdownErrorType,
-    offset: usize
+    position: Position,
+    expected: Option<Position>
 }
 impl MarkdownError {
-    /// Create new `MarkdownError`.
+    /// Create new `MarkdownError` at the offset in chars. The offset is used as the bytes
+    /// and the UTF-16 code units offsets too, so it is exact for an ASCII document, use
+    /// `with_position` to set the full position.
+    /// 
+    /// ### Example:
+    /// ```
+    /// use telemark::parser::types::MarkdownError;
+    /// use telemark::parser::enums::MarkdownErrorType;
+    /// 
+    /// let md_err = MarkdownError::new(MarkdownErrorType::StarOpen, 3);
+    /// assert_eq!(md_err.offset(), 3);
+    /// assert_eq!(md_err.position().byte(), 3);
+    /// ```
     pub fn new(err: MarkdownErrorType, offset: usize) -> Self {
+        return Self::with_position(err, Position::new(offset, offset, offset));
+    }
+
+    /// Create new `MarkdownError` at the position.
+    pub fn with_position(err: MarkdownErrorType, position: Position) -> Self {
         return Self {
             err,
-            offset
+            position,
+            expected: None
         }
     }
 
+    /// Set the position where the closing delimiter was expected.
+    /// 
+    /// ### Example:
+    /// ```
+    /// use telemark::parser::types::MarkdownError;
+    /// use telemark::parser::enums::MarkdownErrorType;
+    /// use telemark::lexer::types::Position;
+    /// 
+    /// let md_err = MarkdownError::new(MarkdownErrorType::StarOpen, 0)
+    ///     .with_expected(Position::new(5, 5, 5));
+    /// assert_eq!(md_err.expected(), Some(Position::new(5, 5, 5)));
+    /// ```
+    pub fn with_expected(mut self, expected: Position) -> Self {
+        self.expected = Some(expected);
+        return self;
+    }
+
     /// Get the error type.
     /// 
     /// ### Example:
@@ -128,7 +233,7 @@ impl MarkdownError {
         return &self.err;
     }
 
-    /// Get the error position.
+    /// Get the error offset in chars, It is the `offset` of `new`.
     /// 
     /// ### Example:
     /// ```
@@ -139,6 +244,41 @@ impl MarkdownError {
     /// assert_eq!(md_err.offset(), 0);
     /// ```
     pub fn offset(&self) -> usize {
-        return self.offset;
+        return self.position.char();
+    }
+
+    /// Get the error position in bytes, chars and UTF-16 code units.
+    /// 
+    /// ### Example:
+    /// ```
+    /// use telemark::parser::mdv1;
+    /// 
+    /// let err = mdv1::parser("مرحبا _italic").unwrap_err();
+    /// assert_eq!(err.offset(), 6);
+    /// assert_eq!(err.position().byte(), 11);
+    /// assert_eq!(err.position().utf16(), 6);
+    /// ```
+    pub fn position(&self) -> Position {
+        return self.position;
+    }
+
+    /// Get the position where the closing delimiter was expected, for the unclosed 
+    /// entities it is the end of the document.
+    /// 
+    /// ### Example:
+    /// ```
+    /// use telemark::parser::mdv1;
+    /// 
+    /// let err = mdv1::parser("*bold").unwrap_err();
+    /// assert_eq!(err.expected().unwrap().char(), 5);
+    /// ```
+    pub fn expected(&self) -> Option<Position> {
+        return self.expected;
+    }
+}
+impl fmt::Display for MarkdownError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "{} at offset {}", self.err, self.offset());
     }
-}
\ No newline at end of file
+}
+impl std::error::Error for MarkdownError {}
@@ -1,47 +1,57 @@
+use crate::lexer::types::Position;
 
-/// The string chars reader.
-pub struct CharReader {
-    current_position: usize,
-    chars: Vec<char>,
+/// The string chars reader, It reads the chars directly from the string
+/// without copying it.
+pub struct CharReader<'a> {
+    string: &'a str,
+    position: Position,
 }
-impl CharReader {
+impl<'a> CharReader<'a> {
     /// Create new `CharReader`.
-    pub fn new(string: &str) -> Self {
+    pub fn new(string: &'a str) -> Self {
         return Self {
-            current_position: 0,
-            chars: string.chars().collect()
+            string,
+            position: Position::default()
+        };
+    }
+
+    /// Create new `CharReader` that starts from `position` in the string.
+    pub fn from_position(string: &'a str, position: Position) -> Self {
+        return Self {
+            string,
+            position
         };
     }
 
     /// Get the next char, It will get the current char and then it will go to the next.
-    pub fn next_char(&mut self) -> Option<&char> {
-        let nc = self.chars.get(self.current_position);
-        self.current_position += 1;
-        return nc;
+    pub fn next_char(&mut self) -> Option<char> {
+        let nc = self.peek_char()?;
+        self.position = self.position.advance(nc);
+        return Some(nc);
     }
 
-    /// Go to the previous char, if the reader in the position 0, it will remain on 0.
-    pub fn go_back(&mut self) {
-        if self.current_position > 0 {
-            self.current_position -= 1;
-        }
+    /// Get the next char without moving the reader.
+    pub fn peek_char(&self) -> Option<char> {
+        return self.string[self.position.byte()..].chars().next();
     }
 
-    /// Get a string slice from the current position to.
-    pub fn get_string(&self, to: usize) -> Option<&[char]> {
-        if self.current_position > self.current_position+to {
-            return None;
+    /// Get a string slice of `to` chars from the current position.
+    pub fn get_string(&self, to: usize) -> Option<&'a str> {
+        let rest = &self.string[self.position.byte()..];
+        let mut chars = rest.char_indices();
+        for _ in 0..to {
+            chars.next()?;
         }
-        return self.chars.get(self.current_position..self.current_position+to);
+        return match chars.next() {
+            Some((end, _)) => Some(&rest[..end]),
+            None => Some(rest)
+        };
     }
 
-    /// Get the current position, if the position is not 0, 
-    /// It will decrease it by 1.
-    pub fn pos(&self) -> usize {
-        if self.current_position == 0 {
-            return 0;
-        }
-        return self.current_position - 1;
+    /// Get the full position (byte, char and UTF-16) of the char that the next 
+    /// `next_char()` will return, if there is no next char, it will return the end of the string.
+    pub fn next_position(&self) -> Position {
+        return self.position;
     }
 }
 
@@ -49,85 +59,104 @@ impl CharReader {
 #[cfg(test)]
 mod char_reader_tests {
     use super::CharReader;
+    use crate::lexer::types::Position;
 
     #[test]
     fn next_char() {
         let mut char_reader = CharReader::new("12");
-        assert_eq!(char_reader.next_char(), Some(&'1'));
-        assert_eq!(char_reader.next_char(), Some(&'2'));
+        assert_eq!(char_reader.next_char(), Some('1'));
+        assert_eq!(char_reader.next_char(), Some('2'));
         assert_eq!(char_reader.next_char(), None);
     }
 
     #[test]
-    fn go_back() {
-        let mut char_reader = CharReader::new("hi");
-        char_reader.go_back();
-        assert_eq!(char_reader.next_char(), Some(&'h')); // next is 'i'
-        char_reader.go_back(); // back to 'h'
-        assert_eq!(char_reader.next_char(), Some(&'h')); // next is 'i'
-        assert_eq!(char_reader.next_char(), Some(&'i'));
-        assert_eq!(char_reader.next_char(), None);
+    fn from_position() {
+        let mut char_reader = CharReader::from_position("é😀!", Position::new(2, 1, 1));
+        assert_eq!(char_reader.next_char(), Some('😀'));
+        assert_eq!(char_reader.next_position(), Position::new(6, 2, 3));
     }
 
     #[test]
-    fn get_string() {
-        let char_reader = CharReader::new("hello world!");
-        if let Some(chars) = char_reader.get_string(5) {
-            assert_eq!(chars.iter().collect::<String>(), "hello");
-        } else {
-            assert!(false, "unexpected None!");
-        }
+    fn peek_char() {
+        let mut char_reader = CharReader::new("é");
+        assert_eq!(char_reader.peek_char(), Some('é'));
+        assert_eq!(char_reader.next_char(), Some('é'));
+        assert_eq!(char_reader.peek_char(), None);
     }
 
     #[test]
-    fn pos() {
+    fn get_string() {
         let mut char_reader = CharReader::new("hello world!");
-        assert_eq!(char_reader.pos(), 0);
-        char_reader.next_char(); // in the first index 'h'
-        assert_eq!(char_reader.pos(), 0); 
+        assert_eq!(char_reader.get_string(5), Some("hello"));
+        assert_eq!(char_reader.get_string(13), None);
+        char_reader.next_char();
+        assert_eq!(char_reader.get_string(11), Some("ello world!"));
+        let char_reader = CharReader::new("😀é!");
+        assert_eq!(char_reader.get_string(2), Some("😀é"));
+    }
+
+    #[test]
+    fn next_position() {
+        let mut char_reader = CharReader::new("é😀");
+        assert_eq!(char_reader.next_position(), Position::new(0, 0, 0));
+        char_reader.next_char(); // 'é'
+        assert_eq!(char_reader.next_position(), Position::new(2, 1, 1));
+        char_reader.next_char(); // '😀'
+        assert_eq!(char_reader.next_position(), Position::new(6, 2, 3));
+        char_reader.next_char(); // end
+        assert_eq!(char_reader.next_position(), Position::new(6, 2, 3));
     }
 }
 
 
-/// The Vec reader.
-pub struct VecReader<T> {
-    current_position: usize,
-    items: Vec<T>,
+/// The Iterator reader, It reads the items lazily from the iterator and 
+/// it can go back by one item.
+pub struct IterReader<I: Iterator> {
+    items: I,
+    current: Option<I::Item>,
+    back: bool,
 }
-impl<T> VecReader<T> {
-    /// Create new `VecReader`.
-    pub fn new(items: Vec<T>) -> Self {
+impl<I: Iterator> IterReader<I> {
+    /// Create new `IterReader`.
+    pub fn new(items: I) -> Self {
         return Self {
-            current_position: 0,
-            items
+            items,
+            current: None,
+            back: false
         }
     }
 
-    /// Get the next item, It will get the current iitem and then it will go to the next.
-    pub fn next(&mut self) -> Option<&T> {
-        let n: Option<&T> = self.items.get(self.current_position);
-        if self.current_position < self.items.len() {
-            self.current_position += 1;
+    /// Get the next item, It will get the current item and then it will go to the next.
+    pub fn next(&mut self) -> Option<&I::Item> {
+        if self.back {
+            self.back = false;
+        } else {
+            self.current = self.items.next();
         }
-        return n;
+        return self.current.as_ref();
     }
 
-    /// Go to the previous item, if the reader in the position 0, it will remain on 0.
+    /// Go back to the last returned item, the reader can go back only one item.
     pub fn go_back(&mut self) {
-        if self.current_position > 0 {
-            self.current_position -= 1;
+        if self.current.is_some() {
+            self.back = true;
         }
     }
+
+    /// Get the iterator, It is ahead of the reader by one item after `go_back`.
+    pub fn items(&self) -> &I {
+        return &self.items;
+    }
 }
 
-/// The VecReader tests.
+/// The IterReader tests.
 #[cfg(test)]
-mod vec_reader_tests {
-    use super::VecReader;
+mod iter_reader_tests {
+    use super::IterReader;
 
     #[test]
     fn next() {
-        let mut reader = VecReader::new(vec![1,2,3]);
+        let mut reader = IterReader::new(vec![1,2,3].into_iter());
         assert_eq!(reader.next(), Some(&1));
         assert_eq!(reader.next(), Some(&2));
         assert_eq!(reader.next(), Some(&3));
@@ -136,10 +165,21 @@ mod vec_reader_tests {
 
     #[test]
     fn go_back() {
-        let mut reader = VecReader::new(vec![1,2,3]);
+        let mut reader = IterReader::new(vec![1,2,3].into_iter());
+        reader.go_back();
+        assert_eq!(reader.next(), Some(&1));
         reader.go_back();
         assert_eq!(reader.next(), Some(&1));
+        assert_eq!(reader.next(), Some(&2));
+    }
+
+    #[test]
+    fn lazy() {
+        let pulled = std::cell::Cell::new(0);
+        let mut reader = IterReader::new((1..).inspect(|_| pulled.set(pulled.get() + 1)));
+        assert_eq!(reader.next(), Some(&1));
         reader.go_back();
         assert_eq!(reader.next(), Some(&1));
+        assert_eq!(pulled.get(), 1);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,218 @@
+use crate::parser::types::Node;
+use crate::parser::enums::Markdown;
+use crate::parser::diagnostic::display_width;
+
+/// The SGR code of the bold text.
+const BOLD: &str = "1";
+/// The SGR code of the dim text, It is used for the code.
+const DIM: &str = "2";
+/// The SGR code of the italic text.
+const ITALIC: &str = "3";
+/// The SGR code of the underlined text.
+const UNDERLINE: &str = "4";
+/// The SGR code of the reverse video, It is used for the spoiler.
+const REVERSE: &str = "7";
+/// The SGR code of the crossed-out text.
+const STRIKETHROUGH: &str = "9";
+
+/// The prefix of the Blockquote lines.
+const QUOTE: &str = "▌ ";
+
+/// Render the AST to a text with the ANSI terminal styles, It is a preview of the
+/// message in the terminal. The bold, italic, underline and strikethrough use their SGR
+/// codes, the spoiler is a reverse video and the code is dim. The Inline URL is an OSC 8
+/// hyperlink, the Pre-Fixed Width Code is drawn in a box with its language on the top
+/// border and every Blockquote line starts with `▌`. The control chars of the text are
+/// shown as their Unicode symbols, so the text can not change the terminal.
+/// 
+/// ### Example:
+/// ```
+/// use telemark::parser::mdv2;
+/// use telemark::render::ansi;
+/// 
+/// let ast = mdv2::parser("*bold _italic_* [link](https://a.com)").unwrap();
+/// assert_eq!(
+///     ansi::render(&ast),
+///     "\x1b[1mbold \x1b[3mitalic\x1b[0m\x1b[1m\x1b[0m \x1b]8;;https://a.com\x1b\\link\x1b]8;;\x1b\\"
+/// );
+/// 
+/// let ast = mdv2::parser("```rust\nlet x = 1;```").unwrap();
+/// assert_eq!(ansi::render(&ast), "┌─ rust ─────┐\n│ let x = 1; │\n└────────────┘");
+/// ```
+pub fn render(node: &Node) -> String {
+    let mut renderer = Renderer {
+        output: String::new(),
+        styles: Vec::new(),
+        quote: false,
+        block: false
+    };
+    renderer.node(node);
+    return renderer.output;
+}
+
+/// The ANSI renderer state.
+struct Renderer {
+    output: String,
+    /// The SGR codes of the open entities.
+    styles: Vec<&'static str>,
+    /// A Blockquote is open.
+    quote: bool,
+    /// The output ends with a box, the next text must start on a new line.
+    block: bool
+}
+impl Renderer {
+    /// Render the node and its inner nodes to the output.
+    fn node(&mut self, node: &Node) {
+        let value = node.value();
+        match value {
+            Markdown::Bold(_) => self.styled(node, BOLD),
+            Markdown::Italic(_) => self.styled(node, ITALIC),
+            Markdown::Underline(_) => self.styled(node, UNDERLINE),
+            Markdown::Strikethrough(_) => self.styled(node, STRIKETHROUGH),
+            Markdown::Spoiler(_) => self.styled(node, REVERSE),
+            Markdown::FixedWidthCode(_) => self.styled(node, DIM),
+            Markdown::PreFormattedFixedWidthCode { lang, code } => self.pre(lang.as_deref(), code),
+            Markdown::InlineURL { .. } | Markdown::TextMention { .. } => {
+                let url = visible(&value.url().unwrap_or_default());
+                self.output += &format!("\x1b]8;;{}\x1b\\", url);
+                self.content(node);
+                self.output += "\x1b]8;;\x1b\\";
+            },
+            Markdown::Blockquote(_) | Markdown::ExpandableBlockquote(_) => {
+                self.write(QUOTE);
+                self.quote = true;
+                self.content(node);
+                self.quote = false;
+            },
+            Markdown::Document
+            | Markdown::Text(_)
+            | Markdown::CustomEmoji { .. }
+            | Markdown::Error(_) => self.content(node)
+        }
+    }
+
+    /// Render the node with the SGR code, The styles are reset at its end and the styles
+    /// of the parents are set again.
+    fn styled(&mut self, node: &Node, code: &'static str) {
+        self.styles.push(code);
+        self.output += &format!("\x1b[{}m", code);
+        self.content(node);
+        self.styles.pop();
+        self.output += "\x1b[0m";
+        self.restore();
+    }
+
+    /// Draw the Pre-Fixed Width Code in a box, the language is on the top border. The box
+    /// width is the display width of the lines, so the wide chars keep the border aligned.
+    fn pre(&mut self, lang: Option<&str>, code: &str) {
+        let lines: Vec<String> = code.split('\n').map(|line| visible(&line.replace('\t', "    "))).collect();
+        let title = lang.map(|lang| format!("─ {} ", visible(lang))).unwrap_or_default();
+        let width = lines.iter()
+            .map(|line| display_width(line))
+            .max()
+            .unwrap_or_default()
+            .max(display_width(&title));
+        if !self.styles.is_empty() {
+            self.output += "\x1b[0m";
+        }
+        if !self.output.is_empty() && !self.output.ends_with('\n') && !self.output.ends_with(QUOTE) {
+            self.write("\n");
+        }
+        let mut drawn = format!("┌{}{}┐\n", title, "─".repeat(width + 2 - display_width(&title)));
+        for line in lines {
+            drawn += &format!("│ {}{} │\n", line, " ".repeat(width - display_width(&line)));
+        }
+        drawn += &format!("└{}┘", "─".repeat(width + 2));
+        self.write(&drawn);
+        self.restore();
+        self.block = true;
+    }
+
+    /// Render the node text or its inner nodes.
+    fn content(&mut self, node: &Node) {
+        if node.inner().is_empty() {
+            self.write(&visible(node.value().text()));
+        }
+        for child in node.inner() {
+            self.node(child);
+        }
+    }
+
+    /// Write the text to the output, In a Blockquote every new line starts with `▌`.
+    fn write(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        if self.block && !text.starts_with('\n') {
+            self.output.push('\n');
+        }
+        self.block = false;
+        if self.quote {
+            self.output += &text.replace('\n', &format!("\n{}", QUOTE));
+        } else {
+            self.output += text;
+        }
+    }
+
+    /// Set the styles of the open entities again.
+    fn restore(&mut self) {
+        if !self.styles.is_empty() {
+            self.output += &format!("\x1b[{}m", self.styles.join(";"));
+        }
+    }
+}
+
+/// Replace the control chars with their Unicode symbols, the new line and the tab are kept.
+fn visible(text: &str) -> String {
+    return text.chars().map(|c| match c {
+        '\n' | '\t' => c,
+        '\u{0}'..='\u{1f}' => char::from_u32(0x2400 + c as u32).unwrap_or('\u{fffd}'),
+        '\u{7f}' => '\u{2421}',
+        c if c.is_control() => '\u{fffd}',
+        _ => c
+    }).collect();
+}
+
+/// The ANSI renderer tests.
+#[cfg(test)]
+mod ansi_render_tests {
+    use super::render;
+    use crate::parser::{html, mdv2};
+
+    #[test]
+    fn styles() {
+        let ast = html::parser("<b>b <code>c</code> b</b> <s>s</s> <tg-spoiler>p</tg-spoiler> <u>u</u>").unwrap();
+        assert_eq!(
+            render(&ast),
+            concat!(
+                "\x1b[1mb \x1b[2mc\x1b[0m\x1b[1m b\x1b[0m ",
+                "\x1b[9ms\x1b[0m \x1b[7mp\x1b[0m \x1b[4mu\x1b[0m"
+            )
+        );
+    }
+
+    #[test]
+    fn pre_box() {
+        let ast = mdv2::parser("*a ```\nx\n\tyz```b*").unwrap();
+        assert_eq!(
+            render(&ast),
+            concat!(
+                "\x1b[1ma \x1b[0m\n┌────────┐\n│ x      │\n│     yz │\n└────────┘",
+                "\x1b[1m\nb\x1b[0m"
+            )
+        );
+        let ast = html::parser("<blockquote>q\n<pre><code class=\"language-sh\">ls</code></pre></blockquote>").unwrap();
+        assert_eq!(render(&ast), "▌ q\n▌ ┌─ sh ──┐\n▌ │ ls    │\n▌ └───────┘");
+        let ast = html::parser("<pre>日本\n😀a\nabc</pre>").unwrap();
+        assert_eq!(render(&ast), "┌──────┐\n│ 日本 │\n│ 😀a  │\n│ abc  │\n└──────┘");
+    }
+
+    #[test]
+    fn links_and_control_chars() {
+        let ast = html::parser("<a href=\"tg://user?id=7\">name</a> \u{1b}[31m <tg-emoji emoji-id=\"5\">👍</tg-emoji>").unwrap();
+        assert_eq!(
+            render(&ast),
+            "\x1b]8;;tg://user?id=7\x1b\\name\x1b]8;;\x1b\\ \u{241b}[31m 👍"
+        );
+    }
+}
@@ -0,0 +1,145 @@
+pub use crate::formatted::{EntityType, MessageEntity};
+use crate::parser::types::Node;
+use crate::parser::enums::Markdown;
+
+/// Convert the AST to the plain text that the recipient sees and its entities, the
+/// entities are sorted by their offsets and the outer entity comes first. The error
+/// nodes are converted to a plain text.
+/// 
+/// ### Example:
+/// ```
+/// use telemark::parser::mdv2;
+/// use telemark::formatted::EntityType;
+/// use telemark::render::entities;
+/// 
+/// let ast = mdv2::parser("😀 *bold _italic_*").unwrap();
+/// let (text, list) = entities::entities(&ast);
+/// assert_eq!(text, "😀 bold italic");
+/// assert_eq!(list[0].entity_type(), &EntityType::Bold);
+/// assert_eq!((list[0].offset(), list[0].length()), (3, 11));
+/// assert_eq!((list[1].offset(), list[1].length()), (8, 6));
+/// ```
+pub fn entities(node: &Node) -> (String, Vec<MessageEntity>) {
+    let mut text = String::new();
+    let mut list: Vec<MessageEntity> = Vec::new();
+    let mut offset: usize = 0;
+    add_node(node, &mut text, &mut list, &mut offset);
+    return (text, list);
+}
+
+/// Write the entities as a Bot API JSON array.
+/// 
+/// ### Example:
+/// ```
+/// use telemark::parser::mdv1;
+/// use telemark::render::entities;
+/// 
+/// let ast = mdv1::parser("```rust\nlet x;``` [a](tg://user?id=7)").unwrap();
+/// let (_, list) = entities::entities(&ast);
+/// assert_eq!(
+///     entities::to_json(&list),
+///     concat!(
+///         "[{\"type\":\"pre\",\"offset\":0,\"length\":6,\"language\":\"rust\"},",
+///         "{\"type\":\"text_mention\",\"offset\":7,\"length\":1,\"user\":{\"id\":7}}]"
+///     )
+/// );
+/// ```
+pub fn to_json(entities: &[MessageEntity]) -> String {
+    let objects: Vec<String> = entities.iter().map(|e| e.to_json()).collect();
+    return format!("[{}]", objects.join(","));
+}
+
+/// Add the node text to the text and its entities to the list.
+fn add_node(node: &Node, text: &mut String, list: &mut Vec<MessageEntity>, offset: &mut usize) {
+    let start = *offset;
+    let index = list.len();
+    if node.inner().is_empty() {
+        let value = node.value().text();
+        *text += value;
+        *offset += value.encode_utf16().count();
+    }
+    for child in node.inner() {
+        add_node(child, text, list, offset);
+    }
+    let length = *offset - start;
+    let entity = match node.value() {
+        Markdown::Bold(_) => MessageEntity::new(EntityType::Bold, start, length),
+        Markdown::Italic(_) => MessageEntity::new(EntityType::Italic, start, length),
+        Markdown::Underline(_) => MessageEntity::new(EntityType::Underline, start, length),
+        Markdown::Strikethrough(_) => MessageEntity::new(EntityType::Strikethrough, start, length),
+        Markdown::Spoiler(_) => MessageEntity::new(EntityType::Spoiler, start, length),
+        Markdown::FixedWidthCode(_) => MessageEntity::new(EntityType::Code, start, length),
+        Markdown::PreFormattedFixedWidthCode { lang: Some(lang), .. } => {
+            MessageEntity::new(EntityType::Pre, start, length).with_language(lang)
+        },
+        Markdown::PreFormattedFixedWidthCode { lang: None, .. } => MessageEntity::new(EntityType::Pre, start, length),
+        Markdown::InlineURL { url, .. } => MessageEntity::new(EntityType::TextLink, start, length).with_url(url),
+        Markdown::TextMention { user_id, .. } => {
+            MessageEntity::new(EntityType::TextMention, start, length).with_user_id(*user_id)
+        },
+        Markdown::CustomEmoji { id, .. } => {
+            MessageEntity::new(EntityType::CustomEmoji, start, length).with_custom_emoji_id(*id)
+        },
+        Markdown::Blockquote(_) => MessageEntity::new(EntityType::Blockquote, start, length),
+        Markdown::ExpandableBlockquote(_) => MessageEntity::new(EntityType::ExpandableBlockquote, start, length),
+        Markdown::Document | Markdown::Text(_) | Markdown::Error(_) => return
+    };
+    if length > 0 {
+        // The outer entity comes before its inner entities.
+        list.insert(index, entity);
+    }
+}
+
+/// The entities export tests.
+#[cfg(test)]
+mod entities_tests {
+    use super::{entities, to_json};
+    use crate::formatted::{json_string, EntityType, MessageEntity};
+    use crate::parser::{html, mdv2};
+
+    #[test]
+    fn utf16_offsets() {
+        let ast = html::parser("𝕏 <b>مرحبا <i>😀</i></b> <a href=\"https://a.com\">é</a>").unwrap();
+        let (text, list) = entities(&ast);
+        assert_eq!(text, "𝕏 مرحبا 😀 é");
+        assert_eq!(list, vec![
+            MessageEntity::new(EntityType::Bold, 3, 8),
+            MessageEntity::new(EntityType::Italic, 9, 2),
+            MessageEntity::new(EntityType::TextLink, 12, 1).with_url("https://a.com"),
+        ]);
+    }
+
+    #[test]
+    fn all_types() {
+        let ast = mdv2::parser(concat!(
+            "__u__~s~||p||`c`![👍](tg://emoji?id=5)\n",
+            ">q\n",
+            "**>e||"
+        )).unwrap();
+        let (text, list) = entities(&ast);
+        assert_eq!(text, "uspc👍\nq\ne");
+        let types: Vec<(&EntityType, usize, usize)> = list.iter()
+            .map(|e| (e.entity_type(), e.offset(), e.length()))
+            .collect();
+        assert_eq!(types, vec![
+            (&EntityType::Underline, 0, 1),
+            (&EntityType::Strikethrough, 1, 1),
+            (&EntityType::Spoiler, 2, 1),
+            (&EntityType::Code, 3, 1),
+            (&EntityType::CustomEmoji, 4, 2),
+            (&EntityType::Blockquote, 7, 1),
+            (&EntityType::ExpandableBlockquote, 9, 1),
+        ]);
+        assert_eq!(list[4].custom_emoji_id(), Some(5));
+        assert_eq!(
+            to_json(&list[4..5]),
+            "[{\"type\":\"custom_emoji\",\"offset\":4,\"length\":2,\"custom_emoji_id\":\"5\"}]"
+        );
+    }
+
+    #[test]
+    fn json_escaping() {
+        assert_eq!(json_string("a\"b\\c\nd\u{1}é"), "\"a\\\"b\\\\c\\nd\\u0001é\"");
+        assert_eq!(to_json(&[]), "[]");
+    }
+}
@@ -0,0 +1,115 @@
+use crate::parser::types::Node;
+use crate::parser::enums::Markdown;
+
+/// Escape the text for the Telegram HTML, the `<`, `>`, `&` and `"` are
+/// replaced with their HTML entities.
+/// 
+/// ### Example:
+/// ```
+/// use telemark::render::html::escape;
+/// 
+/// assert_eq!(escape("a < b && \"c\""), "a &lt; b &amp;&amp; &quot;c&quot;");
+/// ```
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '<' => escaped += "&lt;",
+            '>' => escaped += "&gt;",
+            '&' => escaped += "&amp;",
+            '"' => escaped += "&quot;",
+            _ => escaped.push(c)
+        }
+    }
+    return escaped;
+}
+
+/// Render the AST to the Telegram HTML, the AST can come from any parser.
+/// The error nodes are rendered as a plain text.
+/// 
+/// ### Example:
+/// ```
+/// use telemark::parser::mdv1;
+/// use telemark::render::html;
+/// 
+/// let ast = mdv1::parser("*a < b* [link](https://example.com?a=1&b=2) ```rust\nlet x;```").unwrap();
+/// assert_eq!(
+///     html::render(&ast),
+///     concat!(
+///         "<b>a &lt; b</b> <a href=\"https://example.com?a=1&amp;b=2\">link</a> ",
+///         "<pre><code class=\"language-rust\">let x;</code></pre>"
+///     )
+/// );
+/// ```
+pub fn render(node: &Node) -> String {
+    let mut output = String::new();
+    render_node(node, &mut output);
+    return output;
+}
+
+/// Render the node and its inner nodes to the output.
+fn render_node(node: &Node, output: &mut String) {
+    let (open, close) = match node.value() {
+        Markdown::Document | Markdown::Text(_) | Markdown::Error(_) => (String::new(), ""),
+        Markdown::Bold(_) => ("<b>".to_string(), "</b>"),
+        Markdown::Italic(_) => ("<i>".to_string(), "</i>"),
+        Markdown::Underline(_) => ("<u>".to_string(), "</u>"),
+        Markdown::Strikethrough(_) => ("<s>".to_string(), "</s>"),
+        Markdown::Spoiler(_) => ("<tg-spoiler>".to_string(), "</tg-spoiler>"),
+        Markdown::FixedWidthCode(_) => ("<code>".to_string(), "</code>"),
+        Markdown::PreFormattedFixedWidthCode { lang: Some(lang), .. } => (
+            format!("<pre><code class=\"language-{}\">", escape(lang)),
+            "</code></pre>"
+        ),
+        Markdown::PreFormattedFixedWidthCode { lang: None, .. } => ("<pre>".to_string(), "</pre>"),
+        Markdown::InlineURL { .. } | Markdown::TextMention { .. } => (
+            format!("<a href=\"{}\">", escape(&node.value().url().unwrap_or_default())),
+            "</a>"
+        ),
+        Markdown::CustomEmoji { id, .. } => (format!("<tg-emoji emoji-id=\"{}\">", id), "</tg-emoji>"),
+        Markdown::Blockquote(_) => ("<blockquote>".to_string(), "</blockquote>"),
+        Markdown::ExpandableBlockquote(_) => ("<blockquote expandable>".to_string(), "</blockquote>")
+    };
+    *output += &open;
+    if node.inner().is_empty() {
+        *output += &escape(node.value().text());
+    }
+    for child in node.inner() {
+        render_node(child, output);
+    }
+    *output += close;
+}
+
+/// The HTML renderer tests.
+#[cfg(test)]
+mod html_render_tests {
+    use super::render;
+    use crate::parser::{html, mdv1, mdv2};
+
+    #[test]
+    fn mdv1_to_html() {
+        let ast = mdv1::parser("_i_ `a&b` ```\ncode``` [name](tg://user?id=7)").unwrap();
+        assert_eq!(
+            render(&ast),
+            "<i>i</i> <code>a&amp;b</code> <pre>code</pre> <a href=\"tg://user?id=7\">name</a>"
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        for document in [
+            "*bold _italic \\< bold_* __u__ ~s~ ||p|| `a<b` ```rust\nfn x() {}```",
+            "[a *b*](https://a.com/?a=1&b=2) ![👍](tg://emoji?id=5368324170671202286)",
+            ">quote *bold\n>line*\n**>shown\n>hidden||",
+        ] {
+            let ast = mdv2::parser(document).unwrap();
+            assert_eq!(html::parser(&render(&ast)).unwrap(), ast, "in {:?}", document);
+        }
+    }
+
+    #[test]
+    fn errors_are_text() {
+        let (ast, _) = mdv1::parse_all("a *b <c>");
+        assert_eq!(render(&ast), "a *b &lt;c&gt;");
+    }
+}
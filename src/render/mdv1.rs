@@ -0,0 +1,344 @@
+use crate::lexer::types::Position;
+use crate::parser::types::{MarkdownError, Node};
+use crate::parser::enums::{Markdown, MarkdownErrorType};
+use crate::parser::url;
+
+/// The Markdown V1 formatting chars, they must be escaped in the text.
+pub const RESERVED: [char; 4] = ['_', '*', '`', '['];
+
+/// Escape the text for the Markdown V1, the formatting chars are escaped.
+/// 
+/// ### Example:
+/// ```
+/// use telemark::render::mdv1::escape;
+/// 
+/// assert_eq!(escape("snake_case * 2"), "snake\\_case \\* 2");
+/// ```
+pub fn escape(text: &str) -> String {
+    return escape_chars(text, &RESERVED);
+}
+
+/// Serialize the AST to the smallest Markdown V1 source that the Markdown V1 parser parses
+/// back to the same AST. The parser adds a text node for every token, so the adjacent texts
+/// are serialized as one text and the ASTs are the same when their texts are joined. The
+/// formatting chars are escaped only where they would start an entity. It refuses the trees
+/// that the Markdown V1 can not write, like the nested entities, the entities that it does
+/// not support and the backslash before a formatting char or a closing delimiter, that is
+/// read as an escape.
+/// 
+/// ### Example:
+/// ```
+/// use telemark::parser::{html, mdv1};
+/// use telemark::parser::enums::MarkdownErrorType;
+/// use telemark::render::entities::entities;
+/// use telemark::render::mdv1::serialize;
+/// 
+/// let ast = html::parser("<b>2*3</b> is 6 (2*3) <a href=\"https://a.com\">[a]</a>").unwrap();
+/// let source = serialize(&ast).unwrap();
+/// assert_eq!(source, "*2\\*3* is 6 (2*3) [[a\\]](https://a.com)");
+/// assert_eq!(entities(&mdv1::parser(&source).unwrap()), entities(&ast));
+/// 
+/// let err = serialize(&html::parser("<b>bold <i>italic</i></b>").unwrap()).unwrap_err();
+/// assert_eq!(err.err(), &MarkdownErrorType::NestedEntity);
+/// ```
+pub fn serialize(node: &Node) -> Result<String, MarkdownError> {
+    let mut output = String::new();
+    serialize_node(node, &mut output)?;
+    return Ok(output);
+}
+
+/// Serialize the node and its inner nodes to the output.
+fn serialize_node(node: &Node, output: &mut String) -> Result<(), MarkdownError> {
+    let value = node.value();
+    let position = node.position();
+    if let Markdown::Document = value {
+        // The parser adds a text node for every token, so the adjacent texts are escaped together.
+        let mut text: Option<(String, Position)> = None;
+        for child in node.inner() {
+            if let Markdown::Text(t) = child.value() {
+                text.get_or_insert_with(|| (String::new(), child.position())).0 += t;
+                continue;
+            }
+            if let Some((t, start)) = text.take() {
+                *output += &escape_text(&t, start)?;
+            }
+            serialize_node(child, output)?;
+        }
+        if let Some((t, start)) = text {
+            *output += &escape_text(&t, start)?;
+        }
+        return Ok(());
+    }
+    if !node.inner().is_empty() {
+        return Err(MarkdownError::with_position(MarkdownErrorType::NestedEntity, position));
+    }
+    if value.text().is_empty() && !matches!(value, Markdown::Text(_)) {
+        return Err(MarkdownError::with_position(MarkdownErrorType::EmptyEntity, position));
+    }
+    // The backslash at the end of the text would escape the entity opening delimiter.
+    if !matches!(value, Markdown::Text(_)) && output.ends_with('\\') {
+        return Err(MarkdownError::with_position(
+            MarkdownErrorType::UnrepresentableCharacter('\\'),
+            Position::new(position.byte().saturating_sub(1), position.char().saturating_sub(1), position.utf16().saturating_sub(1))
+        ));
+    }
+    match value {
+        Markdown::Text(text) => *output += &escape_text(text, position)?,
+        Markdown::Bold(text) => *output += &format!("*{}*", escape_content(text, &['*'], position.advance('*'))?),
+        Markdown::Italic(text) => *output += &format!("_{}_", escape_content(text, &['_'], position.advance('_'))?),
+        Markdown::FixedWidthCode(code) => {
+            if joins_backticks(output, 1) {
+                return Err(MarkdownError::with_position(MarkdownErrorType::UnrepresentableCharacter('`'), position));
+            }
+            *output += &format!("`{}`", escape_content(code, &['`'], position.advance('`'))?);
+        },
+        Markdown::PreFormattedFixedWidthCode { lang, code } => {
+            if joins_backticks(output, 3) {
+                return Err(MarkdownError::with_position(MarkdownErrorType::UnrepresentableCharacter('`'), position));
+            }
+            *output += &serialize_pre(lang.as_deref(), code, position)?;
+        },
+        Markdown::InlineURL { title, .. } | Markdown::TextMention { name: title, .. } => {
+            let url = value.url().unwrap_or_default();
+            let title_start = position.advance('[');
+            let url_start = title_start.advance_str(title).advance_str("](");
+            if url.trim().is_empty() {
+                return Err(MarkdownError::with_position(MarkdownErrorType::EmptyURL, url_start));
+            }
+            if let Err(err) = url::validate(&url) {
+                return Err(MarkdownError::with_position(
+                    MarkdownErrorType::InvalidURL(err.err().clone()),
+                    url_start.advance_str(&url[..err.offset()])
+                ));
+            }
+            // The formatting chars are not allowed in the title.
+            *output += &format!(
+                "[{}]({})",
+                escape_content(title, &['*', '_', '`', ']'], title_start)?,
+                escape_content(&url, &[')'], url_start)?
+            );
+        },
+        Markdown::Underline(_) => return Err(unsupported("underline", position)),
+        Markdown::Strikethrough(_) => return Err(unsupported("strikethrough", position)),
+        Markdown::Spoiler(_) => return Err(unsupported("spoiler", position)),
+        Markdown::CustomEmoji { .. } => return Err(unsupported("custom emoji", position)),
+        Markdown::Blockquote(_) => return Err(unsupported("blockquote", position)),
+        Markdown::ExpandableBlockquote(_) => return Err(unsupported("expandable blockquote", position)),
+        Markdown::Error(_) => return Err(unsupported("error", position)),
+        Markdown::Document => {}
+    }
+    return Ok(());
+}
+
+/// Check if the `count` opening backticks would join the backticks at the end of the
+/// output, the lexer reads a run of backticks as three backticks tokens from its start.
+fn joins_backticks(output: &str, count: usize) -> bool {
+    let end = output.len() - output.trim_end_matches('`').len();
+    return !end.is_multiple_of(3) && end % 3 + count >= 3;
+}
+
+/// Serialize the Pre-Fixed Width Code, only the three backticks close it so the single
+/// backticks are not escaped.
+fn serialize_pre(lang: Option<&str>, code: &str, position: Position) -> Result<String, MarkdownError> {
+    let start = position.advance_str("```");
+    let first_line = match lang {
+        Some(lang) if let Some(i) = lang.find('\n') => return Err(MarkdownError::with_position(
+            MarkdownErrorType::UnrepresentableCharacter('\n'),
+            start.advance_str(&lang[..i])
+        )),
+        Some(lang) => format!("{}\n", escape_pre(lang)),
+        // The first line of a code with many lines would be its language.
+        None if code.contains('\n') => "\n".to_string(),
+        None => String::new()
+    };
+    let start = start.advance_str(&first_line);
+    // The backticks at the end would join the closing backticks.
+    if code.ends_with('`') {
+        let end = code.trim_end_matches('`').len();
+        return Err(MarkdownError::with_position(
+            MarkdownErrorType::UnrepresentableCharacter('`'),
+            start.advance_str(&code[..end])
+        ));
+    }
+    let source = format!("{}{}", first_line, escape_pre(code));
+    // The language and the code are read together until the closing backticks.
+    check_backslash(&source, &[], |rest| rest.starts_with("```"), position.advance_str("```"))?;
+    return Ok(format!("```{}```", source));
+}
+
+/// Escape the text outside the entities, the formatting chars always start an entity
+/// so they are escaped, but inside `(...)` everything is a text.
+fn escape_text(text: &str, start: Position) -> Result<String, MarkdownError> {
+    let mut escaped = String::with_capacity(text.len());
+    let mut chars = text.char_indices().peekable();
+    let mut parentheses = false;
+    while let Some((i, c)) = chars.next() {
+        match c {
+            _ if parentheses => parentheses = c != ')',
+            // The `(` reads the text until the `)`.
+            '(' if let Some(end) = text[i..].find(')') => {
+                let inner = start.advance_str(&text[..i + 1]);
+                check_backslash(&text[i + 1..i + end], &[')'], |_| false, inner)?;
+                parentheses = true;
+            },
+            '(' => return Err(MarkdownError::with_position(
+                MarkdownErrorType::ParenthesesOpen,
+                start.advance_str(&text[..i])
+            )),
+            '*' | '_' | '[' => escaped.push('\\'),
+            '`' => {
+                let mut count: usize = 1;
+                while chars.next_if(|(_, c)| *c == '`').is_some() {
+                    count += 1;
+                }
+                // The single backticks come first, so a run of three or more ends with
+                // escaped three backticks that can not join the next backticks.
+                escaped += &"\\`".repeat(count % 3);
+                escaped += &"\\```".repeat(count / 3);
+                continue;
+            },
+            _ => {}
+        }
+        escaped.push(c);
+    }
+    return Ok(escaped);
+}
+
+/// Escape the `chars` inside an entity, the entity content is read until its closing
+/// delimiter, so only it and the formatting chars of the URL title are escaped.
+fn escape_content(text: &str, chars: &[char], start: Position) -> Result<String, MarkdownError> {
+    check_backslash(text, chars, |rest| rest.starts_with(chars), start)?;
+    return Ok(escape_chars(text, chars));
+}
+
+/// Escape the three backticks in the Pre-Fixed Width Code or its language.
+fn escape_pre(code: &str) -> String {
+    return code.replace("```", "\\```");
+}
+
+/// Check that no backslash of the text that is read until a closing delimiter is an escape.
+/// The backslash escapes the formatting chars and the `closers` after it, and at the end of
+/// the text it escapes the closing delimiter. The backslash before the text that `escaped`
+/// accepts is kept, the serializer writes an escape backslash after it.
+fn check_backslash(
+    text: &str,
+    closers: &[char],
+    escaped: impl Fn(&str) -> bool,
+    start: Position
+) -> Result<(), MarkdownError> {
+    for (i, _) in text.match_indices('\\') {
+        let rest = &text[i + 1..];
+        let escape = match rest.chars().next() {
+            Some(c) => !escaped(rest) && (RESERVED.contains(&c) || closers.contains(&c)),
+            None => true
+        };
+        if escape {
+            return Err(MarkdownError::with_position(
+                MarkdownErrorType::UnrepresentableCharacter('\\'),
+                start.advance_str(&text[..i])
+            ));
+        }
+    }
+    return Ok(());
+}
+
+/// Get the unsupported entity error.
+fn unsupported(entity: &str, position: Position) -> MarkdownError {
+    return MarkdownError::with_position(MarkdownErrorType::UnsupportedEntity(entity.to_string()), position);
+}
+
+/// Escape the `chars` in the text with a backslash.
+pub(crate) fn escape_chars(text: &str, chars: &[char]) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if chars.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    return escaped;
+}
+
+/// The Markdown V1 renderer tests.
+#[cfg(test)]
+mod mdv1_render_tests {
+    use super::serialize;
+    use crate::lexer::types::Position;
+    use crate::parser::{html, mdv1};
+    use crate::parser::enums::{Markdown, MarkdownErrorType};
+    use crate::parser::types::Node;
+    use crate::render::entities::entities;
+
+    #[test]
+    fn round_trip() {
+        let document = "*a_b\\** _i_ `c` ```rust\nlet x = `y`;``` [t\\]](tg://user?id=7) \\_x";
+        let ast = mdv1::parser(document).unwrap();
+        assert_eq!(entities(&mdv1::parser(&serialize(&ast).unwrap()).unwrap()), entities(&ast));
+    }
+
+    #[test]
+    fn minimal_source() {
+        // The smallest source is serialized as it is.
+        for document in [
+            "*a_b\\** _i*_ `c\\`` ```rust\nlet x = `y`;``` [t\\]](tg://user?id=7) \\_x",
+            "(a*b) ] ) \\`\\``` ```x\\```y``` ```one line``` [a[b](https://a.com/(x\\))",
+            "\\a *\\b* _\\\\a_ [\\c](https://a.com) (\\d) `\\\\`` ```x\\y\\```z```",
+        ] {
+            let ast = mdv1::parser(document).unwrap();
+            assert_eq!(serialize(&ast).unwrap(), document);
+        }
+    }
+
+    #[test]
+    fn built_ast() {
+        let mut ast = Node::new(Markdown::Document);
+        for value in [
+            Markdown::Text("`````a* [b] (c_d) ".to_string()),
+            Markdown::FixedWidthCode("```".to_string()),
+            Markdown::Italic("*_*".to_string()),
+            Markdown::PreFormattedFixedWidthCode { lang: None, code: "a\n```\nb".to_string() },
+            Markdown::Bold("*".to_string()),
+            Markdown::Text(" end".to_string()),
+        ] {
+            ast.add_inner(Node::new(value));
+        }
+        let source = serialize(&ast).unwrap();
+        assert_eq!(source, "\\`\\`\\```a\\* \\[b] (c_d) `\\`\\`\\``_*\\_*_```\na\n\\```\nb```*\\** end");
+        assert_eq!(entities(&mdv1::parser(&source).unwrap()), entities(&ast));
+    }
+
+    #[test]
+    fn adjacent_texts() {
+        // The texts are escaped together, the `(` of one text is closed in the next text.
+        let mut ast = Node::new(Markdown::Document);
+        for text in ["a (", "b*", ") c"] {
+            ast.add_inner(Node::new(Markdown::Text(text.to_string())));
+        }
+        assert_eq!(serialize(&ast).unwrap(), "a (b*) c");
+        let ast = mdv1::parser("\\*a\\* (b_c)").unwrap();
+        assert!(ast.inner().len() > 1);
+        assert_eq!(serialize(&ast).unwrap(), "\\*a\\* (b_c)");
+    }
+
+    #[test]
+    fn refused() {
+        for (document, err, offset) in [
+            ("<b>a <i>b</i></b>", MarkdownErrorType::NestedEntity, 0),
+            ("a <u>b</u>", MarkdownErrorType::UnsupportedEntity("underline".to_string()), 2),
+            ("a\\<b>b</b>", MarkdownErrorType::UnrepresentableCharacter('\\'), 1),
+            ("<code>a\\_b</code>", MarkdownErrorType::UnrepresentableCharacter('\\'), 2),
+            ("<b>a\\</b>", MarkdownErrorType::UnrepresentableCharacter('\\'), 2),
+            ("(a\\) b", MarkdownErrorType::UnrepresentableCharacter('\\'), 2),
+            ("<pre>a\\`b</pre>", MarkdownErrorType::UnrepresentableCharacter('\\'), 4),
+            ("a (b <b>c)</b>", MarkdownErrorType::ParenthesesOpen, 2),
+            ("<pre>a`</pre>", MarkdownErrorType::UnrepresentableCharacter('`'), 4),
+            ("<code>a</code><pre>b</pre>", MarkdownErrorType::UnrepresentableCharacter('`'), 14),
+        ] {
+            let err_at = serialize(&html::parser(document).unwrap()).unwrap_err();
+            assert_eq!((err_at.err(), err_at.offset()), (&err, offset), "in {:?}", document);
+        }
+        let mut ast = Node::new(Markdown::Document);
+        ast.add_inner(Node::with_position(Markdown::Bold(String::new()), Position::new(3, 3, 3)));
+        assert_eq!(serialize(&ast).unwrap_err().err(), &MarkdownErrorType::EmptyEntity);
+    }
+}
@@ -0,0 +1,365 @@
+use crate::lexer::mdv2::RESERVED;
+use crate::lexer::types::Position;
+use crate::parser::types::{MarkdownError, Node};
+use crate::parser::enums::{Markdown, MarkdownErrorType};
+use crate::parser::url;
+
+use super::mdv1::escape_chars;
+
+/// The chars that `escape` escapes, they are the reserved chars of the lexer and the `\`.
+pub const ESCAPED: [char; 19] = push(RESERVED, '\\');
+
+/// The chars that are escaped in the text by the serializer, the `\r` is escaped so It is
+/// never read as the italic and underline separator.
+const TEXT: [char; 20] = push(ESCAPED, '\r');
+
+/// The chars that are escaped in the code and the pre.
+const CODE: [char; 2] = ['`', '\\'];
+
+/// The chars that are escaped in the Inline URL.
+const URL: [char; 2] = [')', '\\'];
+
+/// Escape the text for the Markdown V2, all the reserved chars and the `\` are escaped.
+/// 
+/// ### Example:
+/// ```
+/// use telemark::render::mdv2::escape;
+/// 
+/// assert_eq!(escape("1+1=2."), "1\\+1\\=2\\.");
+/// ```
+pub fn escape(text: &str) -> String {
+    return escape_chars(text, &ESCAPED);
+}
+
+/// Add the char to the end of the chars, `M` must be `N + 1`.
+const fn push<const N: usize, const M: usize>(chars: [char; N], c: char) -> [char; M] {
+    assert!(M == N + 1);
+    let mut pushed = [c; M];
+    let mut i = 0;
+    while i < N {
+        pushed[i] = chars[i];
+        i += 1;
+    }
+    return pushed;
+}
+
+/// Serialize the AST to a Markdown V2 source that the Markdown V2 parser parses back to
+/// the same AST, so `mdv2::parser(&serialize(&ast)?) == ast`. Every char is escaped by the
+/// rules of its context, the text, the code and the URL, and the `\r` separates an italic
+/// delimiter from a following underline delimiter. It refuses the trees that the Markdown V2
+/// can not write, like an entity inside the same entity or a blockquote that is not a whole
+/// line. The AST must be like the parsers build it, the adjacent texts are merged and no text
+/// is empty.
+/// 
+/// ### Example:
+/// ```
+/// use telemark::parser::{html, mdv2};
+/// use telemark::parser::enums::MarkdownErrorType;
+/// use telemark::render::mdv2::serialize;
+/// 
+/// let ast = html::parser("<i><u>1.</u></i> <code>a_b</code> <a href=\"https://a.com/(x)\">link</a>").unwrap();
+/// let source = serialize(&ast).unwrap();
+/// assert_eq!(source, "_\r__1\\.___ `a_b` [link](https://a.com/(x\\))");
+/// assert_eq!(mdv2::parser(&source).unwrap(), ast);
+/// 
+/// let err = serialize(&html::parser("<b>a <b>b</b></b>").unwrap()).unwrap_err();
+/// assert_eq!(err.err(), &MarkdownErrorType::NestedEntity);
+/// ```
+pub fn serialize(node: &Node) -> Result<String, MarkdownError> {
+    let mut serializer = Serializer {
+        output: String::new(),
+        end: "",
+        open: Vec::new(),
+        quote: None,
+        quote_end: None
+    };
+    serializer.node(node)?;
+    return Ok(serializer.output);
+}
+
+/// The Markdown V2 serializer state.
+struct Serializer {
+    output: String,
+    /// The delimiter at the end of the output, It is empty if the output ends with a text.
+    end: &'static str,
+    /// The opening delimiters of the open entities, the Inline URLs are `[`.
+    open: Vec<&'static str>,
+    /// The open Blockquote, It is `true` if It is expandable.
+    quote: Option<bool>,
+    /// The output length at the end of the last Blockquote that is not expandable.
+    quote_end: Option<usize>
+}
+impl Serializer {
+    /// Serialize the node and its inner nodes to the output.
+    fn node(&mut self, node: &Node) -> Result<(), MarkdownError> {
+        let value = node.value();
+        let position = node.position();
+        match value {
+            Markdown::Document => return self.document(node),
+            Markdown::Error(_) => return Err(MarkdownError::with_position(
+                MarkdownErrorType::UnsupportedEntity("error".to_string()),
+                position
+            )),
+            Markdown::Text(text) => {
+                self.text(text);
+                return Ok(());
+            },
+            _ => {}
+        }
+        if value.text().is_empty() {
+            return Err(MarkdownError::with_position(MarkdownErrorType::EmptyEntity, position));
+        }
+        match value {
+            Markdown::Bold(_) => self.entity(node, "*")?,
+            Markdown::Italic(_) => self.entity(node, "_")?,
+            Markdown::Underline(_) => self.entity(node, "__")?,
+            Markdown::Strikethrough(_) => self.entity(node, "~")?,
+            Markdown::Spoiler(_) => {
+                let start = self.output.len() + "||".len();
+                self.entity(node, "||")?;
+                // The `||` at the end of a line closes the Expandable Blockquote.
+                if self.quote == Some(true) && self.output[start..].starts_with('\n') {
+                    return Err(MarkdownError::with_position(MarkdownErrorType::UnrepresentableCharacter('\n'), position));
+                }
+            },
+            Markdown::FixedWidthCode(code) => {
+                if !node.inner().is_empty() {
+                    return Err(MarkdownError::with_position(MarkdownErrorType::NestedEntity, position));
+                }
+                self.write(&format!("`{}`", escape_chars(code, &CODE)));
+                self.end = "`";
+            },
+            Markdown::PreFormattedFixedWidthCode { lang, code } => {
+                if !node.inner().is_empty() {
+                    return Err(MarkdownError::with_position(MarkdownErrorType::NestedEntity, position));
+                }
+                // The code closing backtick would be the start of the pre backticks.
+                if self.end == "`" {
+                    return Err(MarkdownError::with_position(MarkdownErrorType::UnrepresentableCharacter('`'), position));
+                }
+                let lang = lang.as_deref().unwrap_or_default();
+                if let Some(i) = lang.find('\n') {
+                    return Err(MarkdownError::with_position(
+                        MarkdownErrorType::UnrepresentableCharacter('\n'),
+                        position.advance_str("```").advance_str(&lang[..i])
+                    ));
+                }
+                self.write(&format!("```{}\n{}```", escape_chars(lang, &CODE), escape_chars(code, &CODE)));
+                self.end = "```";
+            },
+            Markdown::InlineURL { title, .. } | Markdown::TextMention { name: title, .. } => {
+                let url = value.url().unwrap_or_default();
+                let url_start = position.advance('[').advance_str(title).advance_str("](");
+                if url.trim().is_empty() {
+                    return Err(MarkdownError::with_position(MarkdownErrorType::EmptyURL, url_start));
+                }
+                if let Err(err) = url::validate(&url) {
+                    return Err(MarkdownError::with_position(
+                        MarkdownErrorType::InvalidURL(err.err().clone()),
+                        url_start.advance_str(&url[..err.offset()])
+                    ));
+                }
+                self.link(node, "[", &url)?;
+            },
+            Markdown::CustomEmoji { id, .. } => self.link(node, "![", &format!("tg://emoji?id={}", id))?,
+            Markdown::Blockquote(_) => self.blockquote(node, false)?,
+            Markdown::ExpandableBlockquote(_) => self.blockquote(node, true)?,
+            Markdown::Document | Markdown::Text(_) | Markdown::Error(_) => {}
+        }
+        return Ok(());
+    }
+
+    /// Serialize the document nodes, the text after a Blockquote must start with a new line.
+    fn document(&mut self, node: &Node) -> Result<(), MarkdownError> {
+        let mut quote_end: Option<usize> = None;
+        for child in node.inner() {
+            self.node(child)?;
+            if let Some(end) = quote_end.take()
+                && !self.output[end..].starts_with('\n') {
+                return Err(MarkdownError::with_position(MarkdownErrorType::BlockquoteNotAtLineEnd, child.position()));
+            }
+            if matches!(child.value(), Markdown::Blockquote(_) | Markdown::ExpandableBlockquote(_)) {
+                quote_end = Some(self.output.len());
+            }
+        }
+        return Ok(());
+    }
+
+    /// Serialize the Blockquote, It must be a whole line outside any entity.
+    fn blockquote(&mut self, node: &Node, expandable: bool) -> Result<(), MarkdownError> {
+        let position = node.position();
+        if !self.open.is_empty() || self.quote.is_some() {
+            return Err(MarkdownError::with_position(MarkdownErrorType::BlockquoteInEntity, position));
+        }
+        if !self.output.is_empty() && !self.output.ends_with('\n') {
+            return Err(MarkdownError::with_position(MarkdownErrorType::BlockquoteNotAtLineStart, position));
+        }
+        // The `>` after the new line continues the last Blockquote.
+        if !expandable && let Some(end) = self.quote_end && &self.output[end..] == "\n" {
+            return Err(MarkdownError::with_position(MarkdownErrorType::UnrepresentableCharacter('\n'), position));
+        }
+        self.write(if expandable { "**>" } else { ">" });
+        self.quote = Some(expandable);
+        self.content(node)?;
+        self.quote = None;
+        if expandable {
+            self.write("||");
+        }
+        // Only the Blockquote that is not expandable can be continued by the next line.
+        self.quote_end = if expandable { None } else { Some(self.output.len()) };
+        return Ok(());
+    }
+
+    /// Serialize the Inline URL or the Custom Emoji, they can not be nested.
+    fn link(&mut self, node: &Node, open: &'static str, url: &str) -> Result<(), MarkdownError> {
+        if self.open.contains(&"[") {
+            return Err(MarkdownError::with_position(MarkdownErrorType::NestedInlineURL, node.position()));
+        }
+        self.write(open);
+        self.open.push("[");
+        self.content(node)?;
+        self.open.pop();
+        self.write(&format!("]({})", escape_chars(url, &URL)));
+        return Ok(());
+    }
+
+    /// Serialize the entity between its delimiters, It can not be inside the same entity.
+    fn entity(&mut self, node: &Node, delimiter: &'static str) -> Result<(), MarkdownError> {
+        let position = node.position();
+        if self.open.contains(&delimiter) {
+            return Err(MarkdownError::with_position(MarkdownErrorType::NestedEntity, position));
+        }
+        self.delimiter(delimiter, position)?;
+        self.open.push(delimiter);
+        self.content(node)?;
+        self.open.pop();
+        return self.delimiter(delimiter, position);
+    }
+
+    /// Write the delimiter, The lexer reads `___` as `__` and `_`, so the `\r` is written
+    /// between the italic and the underline delimiters and It is ignored by the parser.
+    fn delimiter(&mut self, delimiter: &'static str, position: Position) -> Result<(), MarkdownError> {
+        match (self.end, delimiter) {
+            // Two italics side by side would be an underline delimiter.
+            ("_", "_") => return Err(MarkdownError::with_position(MarkdownErrorType::UnrepresentableCharacter('_'), position)),
+            ("_", "__") => self.write("\r"),
+            _ => {}
+        }
+        self.write(delimiter);
+        self.end = delimiter;
+        return Ok(());
+    }
+
+    /// Serialize the entity text or its inner nodes.
+    fn content(&mut self, node: &Node) -> Result<(), MarkdownError> {
+        if node.inner().is_empty() {
+            self.text(node.value().text());
+            return Ok(());
+        }
+        for child in node.inner() {
+            self.node(child)?;
+        }
+        return Ok(());
+    }
+
+    /// Write the escaped text, In a Blockquote every new line of the text starts with `>`.
+    fn text(&mut self, text: &str) {
+        let text = escape_chars(text, &TEXT);
+        if self.quote.is_some() {
+            self.write(&text.replace('\n', "\n>"));
+        } else {
+            self.write(&text);
+        }
+    }
+
+    /// Write to the output.
+    fn write(&mut self, text: &str) {
+        self.output += text;
+        self.end = "";
+    }
+}
+
+/// The Markdown V2 renderer tests.
+#[cfg(test)]
+mod mdv2_render_tests {
+    use super::serialize;
+    use crate::parser::{html, mdv2};
+    use crate::parser::enums::{Markdown, MarkdownErrorType};
+    use crate::parser::types::Node;
+    use crate::parser::url::URLErrorType;
+
+    #[test]
+    fn html_to_mdv2() {
+        let ast = html::parser("<pre>a\\b</pre>\n<blockquote>x <b>y\nz</b></blockquote>").unwrap();
+        assert_eq!(serialize(&ast).unwrap(), "```\na\\\\b```\n>x *y\n>z*");
+    }
+
+    #[test]
+    fn serialized() {
+        for document in [
+            "*bold _italic \\> bold_* __u__ ~s~ ||p|| `a\\`b\\\\` ```rust\nfn x() {}```",
+            "[a *b*](https://a.com/?a=1&b=\\)) ![👍](tg://emoji?id=5368324170671202286) [m](tg://user?id=7)",
+            ">quote *bold\n>line*\n**>shown\n>||spoiler||||\n1\\. \\#end\\!",
+        ] {
+            let ast = mdv2::parser(document).unwrap();
+            assert_eq!(serialize(&ast).unwrap(), document);
+        }
+    }
+
+    #[test]
+    fn italic_underline() {
+        for (document, source) in [
+            ("<i><u>a</u></i>", "_\r__a___"),
+            ("<u><i>a</i></u>", "___a_\r__"),
+            ("<i>a</i><u>b</u> <u>c</u><i>d</i>", "_a_\r__b__ __c___d_"),
+            ("a\r<u>b</u>", "a\\\r__b__"),
+        ] {
+            let ast = html::parser(document).unwrap();
+            let serialized = serialize(&ast).unwrap();
+            assert_eq!(serialized, source);
+            assert_eq!(mdv2::parser(&serialized).unwrap(), ast);
+        }
+    }
+
+    #[test]
+    fn built_ast() {
+        let mut ast = Node::new(Markdown::Document);
+        for value in [
+            Markdown::Text("a\n".to_string()),
+            Markdown::Blockquote("b\n".to_string()),
+            Markdown::Text("\n".to_string()),
+            Markdown::PreFormattedFixedWidthCode { lang: None, code: "``\\".to_string() },
+            Markdown::FixedWidthCode("\\`".to_string()),
+            Markdown::Text("\n".to_string()),
+            Markdown::ExpandableBlockquote("c".to_string()),
+        ] {
+            ast.add_inner(Node::new(value));
+        }
+        let source = serialize(&ast).unwrap();
+        assert_eq!(source, "a\n>b\n>\n```\n\\`\\`\\\\````\\\\\\``\n**>c||");
+        assert_eq!(mdv2::parser(&source).unwrap(), ast);
+    }
+
+    #[test]
+    fn refused() {
+        for (document, err, offset) in [
+            ("<b>a <i>b <b>c</b></i></b>", MarkdownErrorType::NestedEntity, 10),
+            ("<a href=\"https://a.com\">a <tg-emoji emoji-id=\"5\">👍</tg-emoji></a>", MarkdownErrorType::NestedInlineURL, 26),
+            ("<i>a</i><i>b</i>", MarkdownErrorType::UnrepresentableCharacter('_'), 8),
+            ("<code>a</code><pre>b</pre>", MarkdownErrorType::UnrepresentableCharacter('`'), 14),
+            ("a <blockquote>b</blockquote>", MarkdownErrorType::BlockquoteNotAtLineStart, 2),
+            ("<blockquote>a</blockquote><b>b</b>", MarkdownErrorType::BlockquoteNotAtLineEnd, 26),
+            ("<blockquote>a</blockquote>\n<blockquote>b</blockquote>", MarkdownErrorType::UnrepresentableCharacter('\n'), 27),
+            ("<blockquote expandable>a <tg-spoiler>\nb</tg-spoiler></blockquote>", MarkdownErrorType::UnrepresentableCharacter('\n'), 25),
+        ] {
+            let err_at = serialize(&html::parser(document).unwrap()).unwrap_err();
+            assert_eq!((err_at.err(), err_at.offset()), (&err, offset), "in {:?}", document);
+        }
+        let mut bold = Node::new(Markdown::Bold("a".to_string()));
+        bold.add_inner(Node::new(Markdown::Blockquote("a".to_string())));
+        assert_eq!(serialize(&bold).unwrap_err().err(), &MarkdownErrorType::BlockquoteInEntity);
+        let link = Node::new(Markdown::InlineURL { title: "a".to_string(), url: "ftp://a.com".to_string() });
+        let err = serialize(&link).unwrap_err();
+        assert_eq!((err.err(), err.offset()), (&MarkdownErrorType::InvalidURL(URLErrorType::UnsupportedScheme), 4));
+    }
+}
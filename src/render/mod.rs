@@ -0,0 +1,6 @@
+pub mod html;
+pub mod plain;
+pub mod entities;
+pub mod mdv1;
+pub mod mdv2;
+pub mod ansi;
\ No newline at end of file
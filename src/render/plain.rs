@@ -0,0 +1,79 @@
+use crate::parser::types::Node;
+
+/// Render the AST to the plain text that the recipient sees, all the markup and the
+/// escapes are removed. The Pre-Fixed Width Code shows its code without the language
+/// line, the Inline URL shows its title and the Custom Emoji shows its alternative emoji.
+/// The error nodes are rendered as their source. The whitespaces at the start and the end
+/// are kept, so the text is the same as the text of `entities::entities`.
+/// 
+/// ### Example:
+/// ```
+/// use telemark::parser::{html, mdv2};
+/// use telemark::render::plain;
+/// 
+/// let ast = mdv2::parser("*1\\+1* is [two](https://a.com)\n```rust\nlet x = 2;\n```").unwrap();
+/// assert_eq!(plain::render(&ast), "1+1 is two\nlet x = 2;\n");
+/// 
+/// let ast = html::parser(" <b>a &lt; b</b> <tg-emoji emoji-id=\"5\">👍</tg-emoji>\n").unwrap();
+/// assert_eq!(plain::render(&ast), " a < b 👍\n");
+/// ```
+pub fn render(node: &Node) -> String {
+    let mut output = String::new();
+    render_node(node, &mut output);
+    return output;
+}
+
+/// Render the visible text of the node and its inner nodes to the output.
+fn render_node(node: &Node, output: &mut String) {
+    if node.inner().is_empty() {
+        *output += node.value().text();
+    }
+    for child in node.inner() {
+        render_node(child, output);
+    }
+}
+
+/// The plain text renderer tests.
+#[cfg(test)]
+mod plain_render_tests {
+    use super::render;
+    use crate::parser::{html, mdv1, mdv2};
+
+    #[test]
+    fn same_text_in_all_modes() {
+        let expected = "bold italic code\nfn main() {}\nname";
+        for ast in [
+            mdv1::parser("*bold* _italic_ `code`\n```rust\nfn main() {}```\n[name](tg://user?id=7)").unwrap(),
+            mdv2::parser("*bold* _italic_ `code`\n```rust\nfn main() {}```\n[name](tg://user?id=7)").unwrap(),
+            html::parser(concat!(
+                "<b>bold</b> <i>italic</i> <code>code</code>\n",
+                "<pre><code class=\"language-rust\">fn main() {}</code></pre>\n",
+                "<a href=\"tg://user?id=7\">name</a>"
+            )).unwrap(),
+        ] {
+            assert_eq!(render(&ast), expected);
+        }
+    }
+
+    #[test]
+    fn pre_language_line() {
+        // The first line is the language only if the code has more lines.
+        assert_eq!(render(&mdv1::parser("```python```").unwrap()), "python");
+        assert_eq!(render(&mdv1::parser("```\nx = 1```").unwrap()), "x = 1");
+        assert_eq!(render(&mdv2::parser("```py\nx = 1\ny = 2```").unwrap()), "x = 1\ny = 2");
+    }
+
+    #[test]
+    fn escapes_and_errors() {
+        assert_eq!(render(&mdv2::parser("\\*not bold\\* \\_ \\\\").unwrap()), "*not bold* _ \\");
+        let (ast, _) = mdv1::parse_all("a *b");
+        assert_eq!(render(&ast), "a *b");
+    }
+
+    #[test]
+    fn same_text_as_entities() {
+        let ast = mdv2::parser("  *a*  ").unwrap();
+        assert_eq!(render(&ast), "  a  ");
+        assert_eq!(render(&ast), crate::render::entities::entities(&ast).0);
+    }
+}